@@ -0,0 +1,535 @@
+//! `#[derive(Encodable)]`, see `bitwise_challenge_bddap::cheeky_encoding::Encodable`.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Error, Expr, Fields, parse_macro_input, spanned::Spanned};
+
+#[proc_macro_derive(Encodable, attributes(encode))]
+pub fn derive_encodable(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let body = match &input.data {
+        Data::Struct(data) => derive_struct(&data.fields),
+        Data::Enum(data) => derive_enum(data),
+        Data::Union(_) => Err(Error::new(input.span(), "unions can't derive `Encodable`")),
+    };
+    let body = match body {
+        Ok(body) => body,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    quote! {
+        impl #impl_generics ::bitwise_challenge_bddap::cheeky_encoding::Encodable
+            for #name #type_generics #where_clause
+        {
+            #body
+        }
+    }
+    .into()
+}
+
+/// A field as the generated code sees it.
+struct Field {
+    /// How to get at it from `self`, `0` for tuple structs.
+    member: syn::Member,
+    /// How `fields` names it.
+    name: String,
+    /// A local variable to decode it into.
+    var: syn::Ident,
+    ty: syn::Type,
+    /// From `#[encode(cardinality = ..)]`.
+    cardinality: Option<Expr>,
+    /// From `#[encode(len = ..)]`.
+    len: Option<syn::Member>,
+}
+
+fn fields(fields: &Fields) -> syn::Result<Vec<Field>> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            let member = match &field.ident {
+                Some(ident) => syn::Member::Named(ident.clone()),
+                None => syn::Member::Unnamed(index.into()),
+            };
+            let name = match &field.ident {
+                Some(ident) => ident.to_string(),
+                None => index.to_string(),
+            };
+            let mut cardinality = None;
+            let mut len = None;
+            for attr in field.attrs.iter().filter(|a| a.path().is_ident("encode")) {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("cardinality") {
+                        cardinality = Some(meta.value()?.parse()?);
+                    } else if meta.path.is_ident("len") {
+                        len = Some(meta.value()?.parse()?);
+                    } else {
+                        return Err(meta.error("expected `cardinality = ..` or `len = ..`"));
+                    }
+                    Ok(())
+                })?;
+            }
+            Ok(Field {
+                member,
+                name,
+                var: format_ident!("field_{index}"),
+                ty: field.ty.clone(),
+                cardinality,
+                len,
+            })
+        })
+        .collect()
+}
+
+/// The code for a struct's fields, or a variant's, packed as a product.
+struct Product {
+    /// An `Option<__State>` of the biggest index.
+    max: TokenStream,
+    /// Statements writing every field to `writer`.
+    write: TokenStream,
+    /// Statements reading every field from `reader` into its `var`, back to front.
+    read: TokenStream,
+    /// `path` built from the `var`s.
+    construct: TokenStream,
+    /// Statements adding every field's fields to `fields`.
+    list: TokenStream,
+}
+
+/// `access` turns a field into an expression for a reference to it.
+///
+/// Fields with a `len` aren't packed where they are but together with the field holding their
+/// length, as a sum over the lengths (see `cheeky_encoding::sequences`).
+fn product(
+    fields: &[Field],
+    shape: &Fields,
+    path: TokenStream,
+    access: impl Fn(&Field) -> TokenStream,
+) -> syn::Result<Product> {
+    let encoding = quote!(::bitwise_challenge_bddap::cheeky_encoding);
+    let mut maxes = Vec::new();
+    let mut write = TokenStream::new();
+    let mut reads = Vec::new();
+    let mut list = TokenStream::new();
+
+    // the field holding each field's length, and the other way around
+    let mut length_of = vec![None; fields.len()];
+    let mut dependents = vec![Vec::new(); fields.len()];
+    for (index, field) in fields.iter().enumerate() {
+        let Some(len) = &field.len else { continue };
+        let length = fields
+            .iter()
+            .position(|field| field.member == *len)
+            .ok_or_else(|| Error::new(len.span(), "no such field"))?;
+        if fields[length].len.is_some() {
+            return Err(Error::new(
+                len.span(),
+                "a field with a `len` can't be the length of another",
+            ));
+        }
+        length_of[index] = Some(length);
+        dependents[length].push(index);
+    }
+
+    for (index, field) in fields.iter().enumerate() {
+        let Field { name, var, ty, .. } = field;
+        let field_ref = access(field);
+
+        if let Some(length) = length_of[index] {
+            let len = length_value(&fields[length], &access(&fields[length]));
+            let length_name = &fields[length].name;
+            let Element {
+                capacity,
+                fields: element_fields,
+                ..
+            } = element(field)?;
+            list.extend(quote! {
+                let len = #encoding::check_len(#len, #capacity, #name, #length_name);
+                let items = (#field_ref)[..len].iter().enumerate();
+                let items = items.flat_map(|(i, item)| {
+                    #encoding::nest(&::std::format!("[{i}]"), #element_fields)
+                });
+                fields.extend(#encoding::nest(#name, items.collect()));
+            });
+            continue;
+        }
+        list.extend(match &field.cardinality {
+            Some(bound) => quote! {
+                fields.extend(#encoding::nest(
+                    #name,
+                    #encoding::Bounded::fields_below(#field_ref, ((#bound) as u128)),
+                ));
+            },
+            None => quote! {
+                fields.extend(#encoding::nest(
+                    #name,
+                    #encoding::Encodable::fields(#field_ref),
+                ));
+            },
+        });
+
+        if !dependents[index].is_empty() {
+            let len = length_value(field, &field_ref);
+            let from_len = match &field.cardinality {
+                Some(_) => quote!(len as #ty),
+                None => quote!(<#ty as #encoding::Encodable>::from_index(len)),
+            };
+            let mut capacities = Vec::new();
+            let mut element_maxes = Vec::new();
+            let mut group_write = TokenStream::new();
+            let mut group_reads = Vec::new();
+            for &dependent in &dependents[index] {
+                let dependent = &fields[dependent];
+                let Element {
+                    ty: element_ty,
+                    capacity,
+                    max: element_max,
+                    write: element_write,
+                    read: element_read,
+                    ..
+                } = element(dependent)?;
+                let dependent_ref = access(dependent);
+                let dependent_var = &dependent.var;
+                let dependent_ty = &dependent.ty;
+                let dependent_name = &dependent.name;
+                // caught at compile time where the length's cardinality is known by then
+                if let Some(bound) = &field.cardinality {
+                    group_write.extend(quote! {
+                        const {
+                            #encoding::assert_capacity(
+                                ((#bound) as u128).pow(<#ty as #encoding::Bounded>::COUNT),
+                                #capacity,
+                            )
+                        };
+                    });
+                }
+                group_write.extend(quote! {
+                    let items = #encoding::check_len(len, #capacity, #dependent_name, #name);
+                    for item in &(#dependent_ref)[..items] {
+                        #element_write
+                    }
+                });
+                group_reads.push(quote! {
+                    let mut #dependent_var: #dependent_ty = ::std::array::from_fn(|_| {
+                        <#element_ty as #encoding::Encodable>::from_index(
+                            <__State as #encoding::State>::from_u128(0),
+                        )
+                    });
+                    for item in #dependent_var[..len as usize].iter_mut().rev() {
+                        *item = #element_read;
+                    }
+                });
+                capacities.push(capacity);
+                element_maxes.push(element_max);
+            }
+            let lengths = value_max(field, quote!(u64));
+            let lengths = quote!(#encoding::lengths(#lengths, &[#(#capacities),*]));
+            let element_max = quote!(#encoding::product_max([#(#element_maxes),*]));
+            let group_reads = group_reads.iter().rev();
+            maxes.push(quote!(#encoding::sequences_max(#lengths, #element_max)));
+            write.extend(quote! {
+                {
+                    let len = #len;
+                    let mut group = #encoding::Writer::new();
+                    #group_write
+                    #encoding::write_sequence(
+                        &mut writer,
+                        #lengths,
+                        &#encoding::fits(#element_max),
+                        len,
+                        group,
+                    );
+                }
+            });
+            reads.push(quote! {
+                let (len, mut group) = #encoding::read_sequence(
+                    &mut reader,
+                    #lengths,
+                    &#encoding::fits(#element_max),
+                );
+                let #var = #from_len;
+                #(#group_reads)*
+            });
+            continue;
+        }
+
+        maxes.push(value_max(field, quote!(__State)));
+        match &field.cardinality {
+            Some(bound) => {
+                let bound = quote!(((#bound) as u128));
+                write.extend(quote! {
+                    #encoding::Bounded::write_below(#field_ref, #bound, &mut writer);
+                });
+                reads.push(quote! {
+                    let #var = <#ty as #encoding::Bounded>::read_below(#bound, &mut reader);
+                });
+            }
+            None => {
+                write.extend(quote! {
+                    writer.write(
+                        #encoding::Encodable::to_index(#field_ref),
+                        #encoding::assert_fits::<__State, #ty>(),
+                    );
+                });
+                reads.push(quote! {
+                    let #var = <#ty as #encoding::Encodable>::from_index(
+                        reader.read(&#encoding::assert_fits::<__State, #ty>()),
+                    );
+                });
+            }
+        }
+    }
+
+    let vars = fields.iter().map(|field| &field.var);
+    let construct = match shape {
+        Fields::Named(_) => {
+            let members = fields.iter().map(|field| &field.member);
+            quote!(#path { #(#members: #vars),* })
+        }
+        Fields::Unnamed(_) => quote!(#path(#(#vars),*)),
+        Fields::Unit => path,
+    };
+    let reads = reads.iter().rev();
+    Ok(Product {
+        max: quote!(#encoding::product_max([#(#maxes),*])),
+        write,
+        read: quote!(#(#reads)*),
+        construct,
+        list,
+    })
+}
+
+/// An `Option<#state>` of the biggest index of a field without a `len`.
+fn value_max(field: &Field, state: TokenStream) -> TokenStream {
+    let encoding = quote!(::bitwise_challenge_bddap::cheeky_encoding);
+    let ty = &field.ty;
+    match &field.cardinality {
+        Some(bound) => quote! {
+            #encoding::power_max(
+                #encoding::bound_max::<#state>((#bound) as u128),
+                <#ty as #encoding::Bounded>::COUNT,
+            )
+        },
+        None => quote!(<#ty as #encoding::Encodable>::max_index::<#state>()),
+    }
+}
+
+/// The value of a field used as a length, as a `u64`.
+fn length_value(field: &Field, field_ref: &TokenStream) -> TokenStream {
+    let encoding = quote!(::bitwise_challenge_bddap::cheeky_encoding);
+    match &field.cardinality {
+        Some(_) => quote!((*#field_ref as u64)),
+        None => quote!(#encoding::Encodable::to_index::<u64>(#field_ref)),
+    }
+}
+
+/// The items of an array field with a `len`.
+struct Element {
+    ty: syn::Type,
+    /// The array's length.
+    capacity: TokenStream,
+    /// An `Option<__State>` of an item's biggest index.
+    max: TokenStream,
+    /// Statements writing `item` to `group`.
+    write: TokenStream,
+    /// An expression reading one from `group`.
+    read: TokenStream,
+    /// An expression for the fields of `item`.
+    fields: TokenStream,
+}
+
+fn element(field: &Field) -> syn::Result<Element> {
+    let encoding = quote!(::bitwise_challenge_bddap::cheeky_encoding);
+    let syn::Type::Array(array) = &field.ty else {
+        return Err(Error::new(field.ty.span(), "only arrays can have a `len`"));
+    };
+    let ty = (*array.elem).clone();
+    let capacity = &array.len;
+    let capacity = quote!(((#capacity) as usize));
+    Ok(match &field.cardinality {
+        Some(bound) => {
+            let bound = quote!(((#bound) as u128));
+            Element {
+                max: quote! {
+                    #encoding::power_max(
+                        #encoding::bound_max::<__State>(#bound),
+                        <#ty as #encoding::Bounded>::COUNT,
+                    )
+                },
+                write: quote!(#encoding::Bounded::write_below(item, #bound, &mut group);),
+                read: quote!(<#ty as #encoding::Bounded>::read_below(#bound, &mut group)),
+                fields: quote!(#encoding::Bounded::fields_below(item, #bound)),
+                ty,
+                capacity,
+            }
+        }
+        None => Element {
+            max: quote!(<#ty as #encoding::Encodable>::max_index::<__State>()),
+            write: quote! {
+                group.write(
+                    #encoding::Encodable::to_index(item),
+                    #encoding::assert_fits::<__State, #ty>(),
+                );
+            },
+            read: quote! {
+                <#ty as #encoding::Encodable>::from_index(
+                    group.read(&#encoding::assert_fits::<__State, #ty>()),
+                )
+            },
+            fields: quote!(#encoding::Encodable::fields(item)),
+            ty,
+            capacity,
+        },
+    })
+}
+
+fn derive_struct(shape: &Fields) -> syn::Result<TokenStream> {
+    let encoding = quote!(::bitwise_challenge_bddap::cheeky_encoding);
+    let fields = fields(shape)?;
+    let Product {
+        max,
+        write,
+        read,
+        construct,
+        list,
+    } = product(&fields, shape, quote!(Self), |field| {
+        let member = &field.member;
+        quote!(&self.#member)
+    })?;
+
+    Ok(quote! {
+        fn max_index<__State: #encoding::State>() -> ::std::option::Option<__State> {
+            #max
+        }
+
+        #[allow(unused_mut)]
+        fn to_index<__State: #encoding::State>(&self) -> __State {
+            let mut writer = #encoding::Writer::new();
+            #write
+            writer.finish()
+        }
+
+        #[allow(unused_mut, unused_variables)]
+        fn from_index<__State: #encoding::State>(index: __State) -> Self {
+            let mut reader = #encoding::Reader::new(index);
+            #read
+            #construct
+        }
+
+        fn fields(&self) -> ::std::vec::Vec<#encoding::Field> {
+            let mut fields = ::std::vec::Vec::new();
+            #list
+            fields
+        }
+    })
+}
+
+/// Enums are sums, every variant gets a range of indices as big as its fields need.
+fn derive_enum(data: &syn::DataEnum) -> syn::Result<TokenStream> {
+    let encoding = quote!(::bitwise_challenge_bddap::cheeky_encoding);
+    if data.variants.is_empty() {
+        return Err(Error::new(
+            data.brace_token.span.join(),
+            "an enum without variants has no values to encode",
+        ));
+    }
+    let count = data.variants.len() as u128;
+    let fieldless = data.variants.iter().all(|v| v.fields.is_empty());
+
+    let mut maxes = Vec::new();
+    let mut write = Vec::new();
+    let mut read = Vec::new();
+    let mut list = Vec::new();
+    for (number, variant) in data.variants.iter().enumerate() {
+        let number = number as u128;
+        let ident = &variant.ident;
+        let fields = fields(&variant.fields)?;
+        let Product {
+            max: variant_max,
+            write: variant_write,
+            read: variant_read,
+            construct,
+            list: variant_list,
+        } = product(&fields, &variant.fields, quote!(Self::#ident), |field| {
+            let var = &field.var;
+            quote!(#var)
+        })?;
+        // matches the variant, binding its fields to their `var`s
+        let pattern = match &variant.fields {
+            Fields::Unit => quote!(Self::#ident),
+            _ => {
+                let members = fields.iter().map(|field| &field.member);
+                let vars = fields.iter().map(|field| &field.var);
+                quote!(Self::#ident { #(#members: #vars),* })
+            }
+        };
+        let name = ident.to_string();
+
+        write.push(quote! {
+            #pattern => {
+                let mut writer = #encoding::Writer::new();
+                #variant_write
+                #encoding::offset([#(#maxes),*], writer.finish())
+            }
+        });
+        read.push(quote! {
+            let max = #encoding::fits(#variant_max);
+            if index <= max {
+                let mut reader = #encoding::Reader::new(index);
+                #variant_read
+                return #construct;
+            }
+            index = #encoding::skip(index, &max);
+        });
+        list.push(quote! {
+            #pattern => {
+                let mut fields = ::std::vec::Vec::new();
+                #variant_list
+                let tag = #encoding::Field {
+                    name: ::std::string::String::new(),
+                    value: #number,
+                    cardinality: #count,
+                };
+                ::std::iter::once(tag)
+                    .chain(#encoding::nest(#name, fields))
+                    .collect()
+            }
+        });
+        maxes.push(variant_max);
+    }
+
+    // an enum of only fieldless variants is a single field, not a tag followed by nothing
+    let fields = (!fieldless).then(|| {
+        quote! {
+            #[allow(unused_variables)]
+            fn fields(&self) -> ::std::vec::Vec<#encoding::Field> {
+                match self {
+                    #(#list)*
+                }
+            }
+        }
+    });
+    Ok(quote! {
+        fn max_index<__State: #encoding::State>() -> ::std::option::Option<__State> {
+            #encoding::sum_max([#(#maxes),*])
+        }
+
+        #[allow(unused_mut, unused_variables)]
+        fn to_index<__State: #encoding::State>(&self) -> __State {
+            #encoding::assert_fits::<__State, Self>();
+            match self {
+                #(#write)*
+            }
+        }
+
+        #[allow(unused_assignments, unused_mut, unused_variables)]
+        fn from_index<__State: #encoding::State>(index: __State) -> Self {
+            let mut index = index;
+            #(#read)*
+            panic!("index out of range")
+        }
+
+        #fields
+    })
+}
@@ -1,19 +1,50 @@
 use std::{
     iter::once,
     ops::{Add, Neg},
+    sync::LazyLock,
 };
 
-use bitwise_challenge_bddap::cheeky_encoding::{decode, encode};
-use bitwise_challenge_bddap::game::{Game, Input, Key, Output};
+use bitwise_challenge_bddap::cheeky_encoding::{Encodable, Field};
+use bitwise_challenge_bddap::game::{
+    Action, Blend, Color, Game, Input, Key, Output,
+    backend::WindowOptions,
+    font,
+    image::{Blit, Image},
+};
 
 const CELLS: u32 = 8;
 const CELL: u32 = 32;
 const SCORE_H: u32 = 64;
 const SCORE_MAX: u8 = 19;
-const FIELD_COUNT: usize = 34;
+const INPUT_Q_MAX: u8 = 8;
+
+/// Whatever is left of the state once `Data`'s other fields are in: where the snake is and
+/// which way it faces, whether it's dead, and its tail and input queue of up to so many turns.
+const PAD: u64 =
+    u64::MAX / (CELLS.pow(2) as u64 * 4 * 2 * turns(SCORE_MAX as u32) * turns(INPUT_Q_MAX as u32));
+
+/// How many sequences of up to `len` turns there are, 3^0 + .. + 3^len.
+const fn turns(len: u32) -> u64 {
+    (3u64.pow(len + 1) - 1) / 2
+}
+
+/// Sprites are 8x8, magenta marks the see-through parts.
+const SPRITE_SCALE: u32 = CELL / 8;
+const SPRITE_KEY: Color = Color::rgb(255, 0, 255);
+
+static HEAD_EAST: LazyLock<Image> =
+    LazyLock::new(|| sprite(include_bytes!("../../assets/longsnake/head_east.ppm")));
+static HEAD_NORTH: LazyLock<Image> =
+    LazyLock::new(|| sprite(include_bytes!("../../assets/longsnake/head_north.ppm")));
+static BODY: LazyLock<Image> =
+    LazyLock::new(|| sprite(include_bytes!("../../assets/longsnake/body.ppm")));
+
+fn sprite(ppm: &[u8]) -> Image {
+    Image::from_ppm(ppm).expect("embedded sprites are valid")
+}
 
 #[repr(u8)]
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Encodable)]
 enum Turn {
     Left = 0,
     Straight = 1,
@@ -41,7 +72,7 @@ impl From<u8> for Turn {
 }
 
 #[repr(u8)]
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Encodable)]
 enum Direction {
     East = 0,
     North = 1,
@@ -101,14 +132,22 @@ impl Add<Turn> for Direction {
 
 struct Snake;
 
+#[derive(Encodable)]
 struct Data {
+    #[encode(cardinality = CELLS)]
     pos: [u32; 2],
     dir: Direction,
+    #[encode(cardinality = SCORE_MAX + 1)]
     score: u8,
+    /// Only the first `score` turns are part of the snake.
+    #[encode(len = score)]
     tail: [Turn; SCORE_MAX as usize],
     is_dead: bool,
-    input_q: [Turn; 8],
+    #[encode(len = input_q_len)]
+    input_q: [Turn; INPUT_Q_MAX as usize],
+    #[encode(cardinality = INPUT_Q_MAX + 1)]
     input_q_len: u8,
+    #[encode(cardinality = PAD)]
     pad: u64,
 }
 
@@ -121,7 +160,7 @@ impl Default for Data {
             tail: [Turn::Straight; 19],
             is_dead: false,
             pad: 0,
-            input_q: [Turn::Straight; 8],
+            input_q: [Turn::Straight; INPUT_Q_MAX as usize],
             input_q_len: 0,
         }
     }
@@ -132,132 +171,12 @@ fn rand(seed: u64) -> u64 {
     x ^ seed ^ x.wrapping_div(21273)
 }
 
-impl Data {
-    fn to_u64s(&self) -> [u64; FIELD_COUNT] {
-        [
-            self.pos[0] as u64,
-            self.pos[1] as u64,
-            self.dir as i8 as u64,
-            self.score as u64,
-            self.tail[0] as u64,
-            self.tail[1] as u64,
-            self.tail[2] as u64,
-            self.tail[3] as u64,
-            self.tail[4] as u64,
-            self.tail[5] as u64,
-            self.tail[6] as u64,
-            self.tail[7] as u64,
-            self.tail[8] as u64,
-            self.tail[9] as u64,
-            self.tail[10] as u64,
-            self.tail[11] as u64,
-            self.tail[12] as u64,
-            self.tail[13] as u64,
-            self.tail[14] as u64,
-            self.tail[15] as u64,
-            self.tail[16] as u64,
-            self.tail[17] as u64,
-            self.tail[18] as u64,
-            self.is_dead as u64,
-            self.input_q[0] as u64,
-            self.input_q[1] as u64,
-            self.input_q[2] as u64,
-            self.input_q[3] as u64,
-            self.input_q[4] as u64,
-            self.input_q[5] as u64,
-            self.input_q[6] as u64,
-            self.input_q[7] as u64,
-            self.input_q_len as u64,
-            self.pad,
-        ]
-    }
-
-    const CARDINALITIES: [u64; FIELD_COUNT] = [
-        CELLS as u64,
-        CELLS as u64,
-        4,
-        SCORE_MAX as u64 + 1,
-        3,
-        3,
-        3,
-        3,
-        3,
-        3,
-        3,
-        3,
-        3,
-        3,
-        3,
-        3,
-        3,
-        3,
-        3,
-        3,
-        3,
-        3,
-        3,
-        2,
-        3,
-        3,
-        3,
-        3,
-        3,
-        3,
-        3,
-        3,
-        8,
-        29,
-    ];
-
-    fn from_u64s(data: [u64; FIELD_COUNT]) -> Self {
-        Self {
-            pos: [data[0] as u32, data[1] as u32],
-            dir: (data[2] as u8).into(),
-            score: data[3] as u8,
-            tail: [
-                (data[4] as u8).into(),
-                (data[5] as u8).into(),
-                (data[6] as u8).into(),
-                (data[7] as u8).into(),
-                (data[8] as u8).into(),
-                (data[9] as u8).into(),
-                (data[10] as u8).into(),
-                (data[11] as u8).into(),
-                (data[12] as u8).into(),
-                (data[13] as u8).into(),
-                (data[14] as u8).into(),
-                (data[15] as u8).into(),
-                (data[16] as u8).into(),
-                (data[17] as u8).into(),
-                (data[18] as u8).into(),
-                (data[19] as u8).into(),
-                (data[20] as u8).into(),
-                (data[21] as u8).into(),
-                (data[22] as u8).into(),
-            ],
-            is_dead: data[23] == 1,
-            input_q: [
-                (data[24] as u8).into(),
-                (data[25] as u8).into(),
-                (data[26] as u8).into(),
-                (data[27] as u8).into(),
-                (data[28] as u8).into(),
-                (data[29] as u8).into(),
-                (data[30] as u8).into(),
-                (data[31] as u8).into(),
-            ],
-            input_q_len: data[32] as u8,
-            pad: data[33],
-        }
-    }
-}
-
 fn make_state(data: Data) -> u64 {
-    encode(&data.to_u64s(), &Data::CARDINALITIES)
+    data.to_index()
 }
 
 fn from_state(state: u64) -> Data {
-    Data::from_u64s(decode(state, &Data::CARDINALITIES))
+    Data::from_index(state)
 }
 
 fn shift(pos: [u32; 2], dir: Direction) -> [u32; 2] {
@@ -271,6 +190,27 @@ impl Game for Snake {
     const NAME: &'static str = "Snake";
     const WIDTH: usize = (CELLS * CELL) as usize;
     const HEIGHT: usize = (CELLS * CELL + SCORE_H) as usize;
+    // the snake moves every 16 ticks, a bit under 4 cells per second
+    const TICK_RATE: u32 = 60;
+    // in the same order as `Direction`
+    const ACTIONS: &'static [Action] = &[
+        Action {
+            name: "East",
+            keys: &[Key::Right],
+        },
+        Action {
+            name: "North",
+            keys: &[Key::Up],
+        },
+        Action {
+            name: "West",
+            keys: &[Key::Left],
+        },
+        Action {
+            name: "South",
+            keys: &[Key::Down],
+        },
+    ];
 
     fn init() -> u64 {
         make_state(Default::default())
@@ -283,6 +223,17 @@ impl Game for Snake {
         data.render(output, input.tick());
         make_state(data)
     }
+
+    fn window_options() -> WindowOptions {
+        WindowOptions {
+            scale: 2,
+            ..Default::default()
+        }
+    }
+
+    fn debug_fields(state: u64) -> Vec<Field> {
+        from_state(state).fields()
+    }
 }
 
 impl Data {
@@ -335,14 +286,8 @@ impl Data {
     }
 
     fn handle_input(&mut self, input: &Input<'_, Snake>) {
-        for key in input.get_keys_pressed() {
-            let new_dir = match key {
-                Key::Right => Direction::East,
-                Key::Left => Direction::West,
-                Key::Up => Direction::North,
-                Key::Down => Direction::South,
-                _ => return,
-            };
+        for action in input.get_actions_pressed() {
+            let new_dir = Direction::from(action as u8);
             if let Some(turn) = new_dir.relative(self.future_dir()) {
                 self.enqueue_turn(turn)
             }
@@ -351,7 +296,7 @@ impl Data {
 
     fn update(&mut self, tick: u64) {
         if self.is_dead {
-            if tick % 10 != 0 {
+            if !tick.is_multiple_of(10) {
                 // run the death animation a bit longer
                 // to allow the player some agony
                 return;
@@ -364,7 +309,7 @@ impl Data {
             return;
         }
 
-        if tick % 16 == 0 {
+        if tick.is_multiple_of(16) {
             let next_turn = self.dequeue_turn();
             self.dir = self.dir + next_turn;
             self.pos = shift(self.pos, self.dir);
@@ -390,14 +335,41 @@ impl Data {
                 let t = t % 16 * 16;
                 t as u8 * self.is_dead as u8
             };
-            let i = i as u8;
-            output.rect(
-                (pos[0] * CELL) as i32,
-                (pos[1] * CELL + SCORE_H) as i32,
-                CELL,
-                CELL,
-                [blue, i * 10, 255 - i * 10],
-            );
+            let [x, y] = [(pos[0] * CELL) as i32, (pos[1] * CELL + SCORE_H) as i32];
+            let shade = i as u8 * 10;
+            output.rect(x, y, CELL, CELL, Color::rgb(255 - shade, shade, blue));
+
+            let blit = Blit {
+                key: Some(SPRITE_KEY),
+                scale: SPRITE_SCALE,
+                ..Blit::default()
+            };
+            if i == 0 {
+                let (head, blit) = match self.dir {
+                    Direction::East => (&*HEAD_EAST, blit),
+                    Direction::West => (
+                        &*HEAD_EAST,
+                        Blit {
+                            flip_x: true,
+                            ..blit
+                        },
+                    ),
+                    Direction::North => (&*HEAD_NORTH, blit),
+                    Direction::South => (
+                        &*HEAD_NORTH,
+                        Blit {
+                            flip_y: true,
+                            ..blit
+                        },
+                    ),
+                };
+                output.image(x, y, head, blit);
+            } else {
+                // the scales are grey, multiplying keeps the segment's color
+                output.set_blend(Blend::Multiply);
+                output.image(x, y, &BODY, blit);
+                output.set_blend(Blend::Normal);
+            }
         }
 
         // fruit
@@ -407,12 +379,20 @@ impl Data {
             (fruit[1] * CELL + SCORE_H) as i32,
             CELL,
             CELL,
-            [0, 255, 0],
+            Color::rgb(0, 255, 0),
         );
 
         // score
-        output.rect(0, 0, CELLS * CELL, SCORE_H, [100, 100, 100]);
-        output.rect(0, 0, self.score as u32 * 5, SCORE_H, [0, 255, 0]);
+        output.rect(0, 0, CELLS * CELL, SCORE_H, Color::rgb(100, 100, 100));
+        let score = format!("SCORE {:02}", self.score);
+        let [w, h] = font::text_size(&score, 4);
+        output.text(
+            ((CELLS * CELL - w) / 2) as i32,
+            ((SCORE_H - h) / 2) as i32,
+            &score,
+            4,
+            Color::rgb(0, 255, 0),
+        );
     }
 }
 
@@ -426,20 +406,43 @@ mod tests {
 
     #[test]
     fn encode_decode_cardinality() {
-        let max = Data::CARDINALITIES.map(|cardinality| cardinality - 1);
-        assert_eq!(Data::from_u64s(max).to_u64s(), max);
+        let max: u64 = Data::max_index().unwrap();
+        let data = from_state(max);
+        assert_eq!(make_state(data), max);
+        for field in from_state(max).fields() {
+            assert_eq!(field.value, field.cardinality - 1, "{}", field.name);
+        }
+
+        // a full queue used to overflow its length
+        let mut data = Data::default();
+        for _ in 0..8 {
+            data.enqueue_turn(Turn::Left);
+        }
+        let data = from_state(make_state(data));
+        assert_eq!(data.input_q_len, 8);
+        assert_eq!(data.input_q, [Turn::Left; 8]);
+    }
 
-        let encoded = encode(&max, &Data::CARDINALITIES);
-        let decoded = decode(encoded, &Data::CARDINALITIES);
-        assert_eq!(decoded, max);
+    #[test]
+    fn debug_fields() {
+        let data = Data {
+            score: 3,
+            ..Default::default()
+        };
+        let fields = Snake::debug_fields(make_state(data));
+        // only the turns the snake has
+        assert_eq!(fields.len(), 4 + 3 + 3);
+        assert_eq!(fields[3].name, "score");
+        assert_eq!((fields[3].value, fields[3].cardinality), (3, 20));
+        assert_eq!(fields[6].name, "tail[2]");
+        assert_eq!(fields[7].name, "is_dead");
+        assert_eq!(fields[8].name, "input_q_len");
+        assert_eq!(fields[9].name, "pad");
     }
 
     #[test]
     fn wasted_data() {
-        let product = Data::CARDINALITIES
-            .into_iter()
-            .map(Into::<u128>::into)
-            .product::<u128>();
+        let product: u128 = Data::cardinality().unwrap();
         let available = u64::MAX as u128 / product;
         let bits: f64 = (available as f64).log2();
         assert_eq!(
@@ -449,6 +452,36 @@ mod tests {
         // dubious correctness ---^, it's at least close to correct
     }
 
+    #[test]
+    fn headless_turn() {
+        use bitwise_challenge_bddap::game::{Event, headless};
+
+        let mut script = vec![vec![]; 17];
+        script[0].push(Event::KeyPressed(Key::Up));
+        let run = headless::run::<Snake>(script);
+
+        let data = from_state(run.state);
+        assert_eq!(data.dir, Direction::North);
+        assert_eq!(data.pos, [4, 2]);
+        assert_eq!(run.frames.len(), 17);
+    }
+
+    #[test]
+    fn golden_render() {
+        use bitwise_challenge_bddap::game::{Event, golden::assert_golden, headless};
+
+        // turn up into the first fruit, then keep going so there is a tail to draw
+        let mut script = vec![vec![]; 60];
+        script[1].push(Event::KeyPressed(Key::Up));
+        let run = headless::run::<Snake>(script);
+        assert_eq!(from_state(run.state).score, 1);
+
+        assert_golden(
+            run.frames.last().unwrap(),
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/longsnake.ppm"),
+        );
+    }
+
     #[test]
     fn parameterized_turns() {
         let test_cases = [
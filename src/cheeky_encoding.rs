@@ -5,21 +5,32 @@
 //!
 //! Some interesting points:
 //! - The encode-decode process must be FIFO (I believe).
-//! - You can encode into a variable-length integer. This is useful if the data size is variable.
+//! - You can encode into a variable-length integer (`Varuint`). This is useful if the data size
+//!   is variable. Any `State` works, games use `u64`.
 //! - This could be a flexible base for probabilistic compression, such as variable-length encoding.
 
-fn too_big(cardinalities: &[u64]) -> bool {
-    let mut product = 1u128;
+use std::fmt;
+
+fn too_big<S: State>(cardinalities: &[u64]) -> bool {
+    let Some(bits) = S::BITS else {
+        return false;
+    };
+    // the biggest state, the product of the cardinalities minus one
+    // a zero makes the product zero, which fits, and `check` reports it
+    if cardinalities.contains(&0) {
+        return false;
+    }
+    let mut max = Varuint::default();
     for &cardinality in cardinalities {
-        product = product.checked_mul(cardinality.into()).unwrap_or(u128::MAX);
+        max.mul_add(cardinality, cardinality - 1);
     }
-    product > u64::MAX as u128 + 1
+    max.bits() > bits.into()
 }
 
 /// # The Rules
 ///
 /// 1. Every value in `data` must be less than the corresponding value in `cardinalities`.
-/// 2. The product of all cardinalities must be no larger than `u64::MAX + 1`.
+/// 2. The product of all cardinalities must be no larger than `S::MAX + 1`.
 /// 3. A cardinality of zero doesn't make sense.
 ///
 /// Debug assertions will catch rule violations, in release you are on your own.
@@ -27,12 +38,12 @@ fn too_big(cardinalities: &[u64]) -> bool {
 /// # Limitations
 ///
 /// 1. This interface provides no way to store an item with cardinality `u64::MAX + 1` even
-///    though rule 2 would allow it. Sure we could probably take `maxes` instead of `cardinalities`.
-///    That would also make rule 3 unbreakable.
-pub fn encode<const N: usize>(data: &[u64; N], cardinalities: &[u64; N]) -> u64 {
-    debug_assert!(!too_big(cardinalities));
+///    though rule 2 would allow it for wider states. Sure we could probably take `maxes`
+///    instead of `cardinalities`. That would also make rule 3 unbreakable. `Encodable` does.
+pub fn encode<S: State, const N: usize>(data: &[u64; N], cardinalities: &[u64; N]) -> S {
+    debug_assert!(!too_big::<S>(cardinalities));
 
-    let mut state = 0;
+    let mut state = S::from_u128(0);
     for (value, cardinality) in data.iter().zip(cardinalities) {
         // for the first element in `data`, the multiply in push is a nop because state is 0
         push(&mut state, *value, *cardinality);
@@ -40,8 +51,8 @@ pub fn encode<const N: usize>(data: &[u64; N], cardinalities: &[u64; N]) -> u64
     state
 }
 
-pub fn decode<const N: usize>(state: u64, cardinalities: &[u64; N]) -> [u64; N] {
-    debug_assert!(!too_big(cardinalities));
+pub fn decode<S: State, const N: usize>(state: S, cardinalities: &[u64; N]) -> [u64; N] {
+    debug_assert!(!too_big::<S>(cardinalities));
 
     let mut result = [0; N];
     let mut state = state;
@@ -51,17 +62,897 @@ pub fn decode<const N: usize>(state: u64, cardinalities: &[u64; N]) -> [u64; N]
     result
 }
 
-fn push(state: &mut u64, value: u64, cardinality: u64) {
+/// Like `encode`, but checks the rules rather than trusting them.
+pub fn try_encode<S: State, const N: usize>(
+    data: &[u64; N],
+    cardinalities: &[u64; N],
+) -> Result<S, Error> {
+    check::<S>(cardinalities)?;
+    if let Some(index) = (0..N).find(|&i| data[i] >= cardinalities[i]) {
+        return Err(Error::ValueOutOfRange(index));
+    }
+    Ok(encode(data, cardinalities))
+}
+
+/// Like `decode`, but checks the rules, and that `state` is one `encode` could have made. Use
+/// it on states from outside, like pasted save codes.
+pub fn try_decode<S: State, const N: usize>(
+    state: S,
+    cardinalities: &[u64; N],
+) -> Result<[u64; N], Error> {
+    check::<S>(cardinalities)?;
+    let mut result = [0; N];
+    let mut state = state;
+    for (i, cardinality) in cardinalities.iter().enumerate().rev() {
+        result[i] = pop(&mut state, *cardinality);
+    }
+    // anything left over is past the product of the cardinalities
+    match state.try_to_u128() {
+        Some(0) => Ok(result),
+        _ => Err(Error::StateOutOfRange),
+    }
+}
+
+/// Rules 2 and 3.
+fn check<S: State>(cardinalities: &[u64]) -> Result<(), Error> {
+    if let Some(index) = cardinalities.iter().position(|&c| c == 0) {
+        return Err(Error::ZeroCardinality(index));
+    }
+    if too_big::<S>(cardinalities) {
+        return Err(Error::Overflow);
+    }
+    Ok(())
+}
+
+/// Why `try_encode` or `try_decode` refused.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The value at this index isn't below its cardinality.
+    ValueOutOfRange(usize),
+    /// The cardinality at this index is zero.
+    ZeroCardinality(usize),
+    /// The product of the cardinalities doesn't fit in the state.
+    Overflow,
+    /// The state is too big to have come from these cardinalities.
+    StateOutOfRange,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ValueOutOfRange(index) => {
+                write!(f, "value {index} is not below its cardinality")
+            }
+            Error::ZeroCardinality(index) => write!(f, "cardinality {index} is zero"),
+            Error::Overflow => write!(f, "too many values to fit in the state"),
+            Error::StateOutOfRange => write!(f, "state is out of range"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+fn push<S: State>(state: &mut S, value: u64, cardinality: u64) {
     debug_assert!(value < cardinality);
-    *state *= cardinality;
-    *state += value;
+    state.mul_add(cardinality, value);
 }
 
-fn pop(state: &mut u64, cardinality: u64) -> u64 {
+fn pop<S: State>(state: &mut S, cardinality: u64) -> u64 {
     debug_assert!(cardinality > 0);
-    let ret = *state % cardinality;
-    *state /= cardinality;
-    ret
+    state.div_rem(cardinality)
+}
+
+/// An integer to encode into. Games use `u64`, the others are for things like save files or
+/// network messages, where a state may need more room or less.
+///
+/// `Encodable` works with maxes, the biggest index something can have, rather than
+/// cardinalities, so that something with exactly `S::MAX + 1` values still fits in `S`.
+pub trait State: Clone + Ord {
+    /// How many bits fit, `None` if it grows as needed.
+    const BITS: Option<u32>;
+
+    /// `self * multiplier + addend`, which has to fit.
+    fn mul_add(&mut self, multiplier: u64, addend: u64);
+
+    /// Divide by `divisor`, returning the remainder.
+    fn div_rem(&mut self, divisor: u64) -> u64;
+
+    /// `self * (max + 1) + value`, packing a `value` of at most `max` after `self`. `None` if
+    /// that doesn't fit.
+    fn try_push(&self, value: &Self, max: &Self) -> Option<Self>;
+
+    /// Undoes `try_push`: divide by `max + 1`, returning the remainder.
+    fn pop(&mut self, max: &Self) -> Self;
+
+    /// `None` if the sum doesn't fit.
+    fn try_add(&self, other: &Self) -> Option<Self>;
+
+    /// `other` has to be no bigger than `self`.
+    fn sub(&self, other: &Self) -> Self;
+
+    /// `value` has to fit.
+    fn from_u128(value: u128) -> Self;
+
+    /// `None` if `self` doesn't fit.
+    fn try_to_u128(&self) -> Option<u128>;
+
+    /// Panics if `self` doesn't fit.
+    fn to_u128(&self) -> u128 {
+        self.try_to_u128().expect("too big for a u128")
+    }
+}
+
+macro_rules! states {
+    ($($int:ty),*) => {$(
+        impl State for $int {
+            const BITS: Option<u32> = Some(<$int>::BITS);
+
+            fn mul_add(&mut self, multiplier: u64, addend: u64) {
+                *self = *self * multiplier as $int + addend as $int;
+            }
+
+            fn div_rem(&mut self, divisor: u64) -> u64 {
+                // in u128 so that divisors bigger than the state work
+                let state = *self as u128;
+                *self = (state / divisor as u128) as $int;
+                (state % divisor as u128) as u64
+            }
+
+            fn try_push(&self, value: &Self, max: &Self) -> Option<Self> {
+                match <$int>::checked_add(*max, 1) {
+                    Some(cardinality) => self.checked_mul(cardinality)?.checked_add(*value),
+                    // `value` takes up the whole state, so nothing can come before it
+                    None => (*self == 0).then_some(*value),
+                }
+            }
+
+            fn pop(&mut self, max: &Self) -> Self {
+                match <$int>::checked_add(*max, 1) {
+                    Some(cardinality) => {
+                        let remainder = *self % cardinality;
+                        *self /= cardinality;
+                        remainder
+                    }
+                    None => std::mem::take(self),
+                }
+            }
+
+            fn try_add(&self, other: &Self) -> Option<Self> {
+                <$int>::checked_add(*self, *other)
+            }
+
+            fn sub(&self, other: &Self) -> Self {
+                *self - *other
+            }
+
+            fn from_u128(value: u128) -> Self {
+                debug_assert!(value <= <$int>::MAX as u128);
+                value as $int
+            }
+
+            fn try_to_u128(&self) -> Option<u128> {
+                Some(*self as u128)
+            }
+        }
+    )*};
+}
+
+states!(u32, u64, u128);
+
+/// `value` as an `S`, `None` if it doesn't fit.
+fn fitting<S: State>(value: u128) -> Option<S> {
+    match S::BITS {
+        Some(bits) if bits < 128 && value >> bits != 0 => None,
+        _ => Some(S::from_u128(value)),
+    }
+}
+
+/// An unsigned integer that grows as big as it needs to be.
+///
+/// `to_bytes` writes it as a LEB128 varint, so small states take few bytes however big they
+/// could have been.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Varuint {
+    /// Least significant first, without zeros at the end.
+    limbs: Vec<u32>,
+}
+
+impl Varuint {
+    /// How many bits it takes, none for zero.
+    pub fn bits(&self) -> u64 {
+        match self.limbs.last() {
+            Some(last) => self.limbs.len() as u64 * 32 - last.leading_zeros() as u64,
+            None => 0,
+        }
+    }
+
+    /// Seven bits per byte, least significant first, with the top bit set on all but the last.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut rest = self.clone();
+        let mut bytes = Vec::new();
+        loop {
+            let byte = rest.div_rem(0x80) as u8;
+            if rest.limbs.is_empty() {
+                bytes.push(byte);
+                return bytes;
+            }
+            bytes.push(byte | 0x80);
+        }
+    }
+
+    /// Reads what `to_bytes` wrote from the start of `bytes`, and says how many bytes that was.
+    /// `None` if `bytes` end first.
+    pub fn from_bytes(bytes: &[u8]) -> Option<(Self, usize)> {
+        let len = bytes.iter().position(|byte| byte & 0x80 == 0)? + 1;
+        let mut value = Self::default();
+        for byte in bytes[..len].iter().rev() {
+            value.mul_add(0x80, (byte & 0x7f).into());
+        }
+        Some((value, len))
+    }
+
+    fn trim(&mut self) {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+    }
+
+    /// A limb of `other` at a time, most significant first.
+    fn mul(&self, other: &Self) -> Self {
+        let mut product = Self::default();
+        for &limb in other.limbs.iter().rev() {
+            product.mul_add(1 << 32, 0);
+            let mut part = self.clone();
+            part.mul_add(limb.into(), 0);
+            product = product.try_add(&part).unwrap();
+        }
+        product
+    }
+
+    /// `div_rem` for divisors of any size. Long division, a bit at a time, unless the divisor
+    /// is small enough for `div_rem`.
+    fn div_rem_big(&mut self, divisor: &Self) -> Self {
+        if let Some(divisor) = divisor.try_to_u128().and_then(|d| u64::try_from(d).ok()) {
+            return Self::from_u128(self.div_rem(divisor).into());
+        }
+        let mut quotient = Self::default();
+        let mut remainder = Self::default();
+        for bit in (0..self.bits()).rev() {
+            let limb = self.limbs[bit as usize / 32];
+            remainder.mul_add(2, (limb >> (bit % 32) & 1).into());
+            let fits = remainder >= *divisor;
+            if fits {
+                remainder = remainder.sub(divisor);
+            }
+            quotient.mul_add(2, fits.into());
+        }
+        *self = quotient;
+        remainder
+    }
+}
+
+impl Ord for Varuint {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // without zeros at the end, more limbs is bigger
+        let len = self.limbs.len().cmp(&other.limbs.len());
+        len.then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl PartialOrd for Varuint {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl State for Varuint {
+    const BITS: Option<u32> = None;
+
+    fn mul_add(&mut self, multiplier: u64, addend: u64) {
+        let mut carry = addend as u128;
+        for limb in &mut self.limbs {
+            let product = *limb as u128 * multiplier as u128 + carry;
+            *limb = product as u32;
+            carry = product >> 32;
+        }
+        while carry > 0 {
+            self.limbs.push(carry as u32);
+            carry >>= 32;
+        }
+        self.trim();
+    }
+
+    fn div_rem(&mut self, divisor: u64) -> u64 {
+        // below `divisor`, so shifting in another limb can't overflow
+        let mut remainder = 0u128;
+        for limb in self.limbs.iter_mut().rev() {
+            let dividend = remainder << 32 | *limb as u128;
+            *limb = (dividend / divisor as u128) as u32;
+            remainder = dividend % divisor as u128;
+        }
+        self.trim();
+        remainder as u64
+    }
+
+    fn try_push(&self, value: &Self, max: &Self) -> Option<Self> {
+        let mut cardinality = max.clone();
+        cardinality.mul_add(1, 1);
+        self.mul(&cardinality).try_add(value)
+    }
+
+    fn pop(&mut self, max: &Self) -> Self {
+        let mut cardinality = max.clone();
+        cardinality.mul_add(1, 1);
+        self.div_rem_big(&cardinality)
+    }
+
+    fn try_add(&self, other: &Self) -> Option<Self> {
+        let len = self.limbs.len().max(other.limbs.len());
+        let mut sum = Self {
+            limbs: Vec::with_capacity(len + 1),
+        };
+        let mut carry = 0;
+        for i in 0..len {
+            let [a, b] = [self, other].map(|x| x.limbs.get(i).copied().unwrap_or(0) as u64);
+            let limb = a + b + carry;
+            sum.limbs.push(limb as u32);
+            carry = limb >> 32;
+        }
+        sum.limbs.push(carry as u32);
+        sum.trim();
+        Some(sum)
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        debug_assert!(self >= other);
+        let mut difference = self.clone();
+        let mut borrow = 0;
+        for (i, limb) in difference.limbs.iter_mut().enumerate() {
+            let subtrahend = other.limbs.get(i).copied().unwrap_or(0) as u64 + borrow;
+            let (limb_difference, underflow) = (*limb as u64).overflowing_sub(subtrahend);
+            *limb = limb_difference as u32;
+            borrow = underflow.into();
+        }
+        difference.trim();
+        difference
+    }
+
+    fn from_u128(value: u128) -> Self {
+        let limbs = (0..4).map(|i| (value >> (i * 32)) as u32);
+        let mut value = Self {
+            limbs: limbs.collect(),
+        };
+        value.trim();
+        value
+    }
+
+    fn try_to_u128(&self) -> Option<u128> {
+        if self.limbs.len() > 4 {
+            return None;
+        }
+        let limbs = self.limbs.iter().enumerate();
+        Some(limbs.fold(0, |value, (i, &limb)| value | (limb as u128) << (i * 32)))
+    }
+}
+
+/// A type whose values can be numbered from 0 up to its `max_index`, so that it packs into a
+/// state with one `to_index` call.
+///
+/// `#[derive(Encodable)]` numbers a struct's fields like `encode` does, the first field being
+/// the most significant. Enums are sums rather than products: each variant, in order, gets as
+/// many indices as its fields need and no more, so `Option<T>` has one more value than `T`.
+/// Integers (or arrays of them) that stay below some bound can say so to take up less room,
+/// and arrays where only the first few items matter can name the field holding how many:
+///
+/// ```
+/// use bitwise_challenge_bddap::cheeky_encoding::Encodable;
+///
+/// #[derive(Encodable)]
+/// enum Light {
+///     Red,
+///     Yellow,
+///     Green,
+/// }
+///
+/// #[derive(Encodable)]
+/// struct Crossing {
+///     lights: [Light; 2],
+///     #[encode(cardinality = 10)]
+///     waiting: [u8; 4],
+///     button: bool,
+///     #[encode(cardinality = 4)]
+///     cars: u8,
+///     /// only `cars` of these are encoded
+///     #[encode(len = cars)]
+///     turning: [bool; 3],
+/// }
+///
+/// #[derive(Encodable)]
+/// enum Screen {
+///     Menu {
+///         #[encode(cardinality = 3)]
+///         cursor: u8,
+///     },
+///     Playing(Crossing),
+///     Paused(Option<Crossing>),
+/// }
+///
+/// let crossing = 3 * 3 * 10_000 * 2 * (1 + 2 + 4 + 8);
+/// assert_eq!(Crossing::cardinality::<u64>(), Some(crossing));
+/// assert_eq!(
+///     Screen::cardinality::<u64>(),
+///     Some(3 + crossing + 1 + crossing),
+/// );
+/// ```
+///
+/// Cardinalities are as big as the state they're asked for in, so there's no limit on how big
+/// a type can be as long as it's encoded into a `Varuint`. Narrower states make `to_index`
+/// panic when the type has more values than they hold.
+///
+/// A count can depend on another field, but a cardinality can't, so there is no saying that one
+/// field stays below another. Numbering those exactly means adding up the dependent field's
+/// cardinality for every value the other field could have had before its own: for a length that
+/// is one term per item of the array, but for a bound it's one per value of the other field,
+/// which could be billions, on every `to_index`. Where there are few, an enum with a variant per
+/// value packs them exactly.
+pub trait Encodable: Sized {
+    /// The biggest index, one less than how many values there are, so that a `u64` fits in a
+    /// `u64`. `None` if `S` can't hold it.
+    fn max_index<S: State>() -> Option<S>;
+
+    /// How many values there are, `None` if `S` can't hold that.
+    fn cardinality<S: State>() -> Option<S> {
+        Self::max_index::<S>()?.try_add(&S::from_u128(1))
+    }
+
+    /// At most `max_index`. Panics if `S` is too small to hold that.
+    fn to_index<S: State>(&self) -> S;
+
+    /// `index` must be at most `max_index`.
+    fn from_index<S: State>(index: S) -> Self;
+
+    /// Like `from_index`, for indices from outside that might be past `max_index`.
+    fn try_from_index<S: State>(index: S) -> Result<Self, Error> {
+        match Self::max_index::<S>() {
+            None => Err(Error::Overflow),
+            Some(max) if index > max => Err(Error::StateOutOfRange),
+            Some(_) => Ok(Self::from_index(index)),
+        }
+    }
+
+    /// The plain values `self` is made of, named by where they are in it, like `pos[1]`.
+    /// Things that aren't made of anything have a single field with an empty name.
+    fn fields(&self) -> Vec<Field> {
+        vec![Field {
+            name: String::new(),
+            value: self.to_index(),
+            cardinality: Self::cardinality().expect("too many values for a field"),
+        }]
+    }
+}
+
+pub use bitwise_challenge_bddap_derive::Encodable;
+
+/// One plain value something is made of, see `Encodable::fields`. Games also hand these to the
+/// runner's debug overlay.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    pub value: u128,
+    /// How many values the field can take.
+    pub cardinality: u128,
+}
+
+const TOO_BIG: &str = "too many values to fit in the state";
+
+/// `T::max_index`, panicking if it doesn't fit.
+#[doc(hidden)]
+pub fn assert_fits<S: State, T: Encodable>() -> S {
+    T::max_index().expect(TOO_BIG)
+}
+
+/// Panics if `max` doesn't fit.
+#[doc(hidden)]
+pub fn fits<S: State>(max: Option<S>) -> S {
+    max.expect(TOO_BIG)
+}
+
+/// The biggest index of something made of parts whose biggest indices are `maxes`.
+#[doc(hidden)]
+pub fn product_max<S: State>(maxes: impl IntoIterator<Item = Option<S>>) -> Option<S> {
+    let mut product = S::from_u128(0);
+    for max in maxes {
+        let max = max?;
+        product = product.try_push(&max, &max)?;
+    }
+    Some(product)
+}
+
+/// The biggest index of `count` things, each at most `max`.
+#[doc(hidden)]
+pub fn power_max<S: State>(max: Option<S>, count: u32) -> Option<S> {
+    product_max((0..count).map(|_| max.clone()))
+}
+
+/// The biggest index below `bound`, as for `#[encode(cardinality = ..)]`.
+#[doc(hidden)]
+pub fn bound_max<S: State>(bound: u128) -> Option<S> {
+    assert!(bound > 0, "a cardinality of zero doesn't make sense");
+    fitting(bound - 1)
+}
+
+/// How many values a sum of things, whose biggest indices are `maxes`, can take. Also the
+/// index of the first value of whatever comes after them.
+#[doc(hidden)]
+pub fn sum<S: State>(maxes: impl IntoIterator<Item = Option<S>>) -> Option<S> {
+    let one = S::from_u128(1);
+    let mut sum = S::from_u128(0);
+    for max in maxes {
+        sum = sum.try_add(&max?)?.try_add(&one)?;
+    }
+    Some(sum)
+}
+
+/// The biggest index of a sum, see `sum`.
+#[doc(hidden)]
+pub fn sum_max<S: State>(maxes: impl IntoIterator<Item = Option<S>>) -> Option<S> {
+    Some(sum(maxes)?.sub(&S::from_u128(1)))
+}
+
+/// `index - max - 1`, the index past something whose biggest index is `max`.
+#[doc(hidden)]
+pub fn skip<S: State>(index: S, max: &S) -> S {
+    index.sub(max).sub(&S::from_u128(1))
+}
+
+/// Packs values front to back, like `encode`.
+#[doc(hidden)]
+pub struct Writer<S> {
+    index: S,
+    /// The biggest index of everything written, which has to fit too.
+    max: S,
+}
+
+impl<S: State> Writer<S> {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            index: S::from_u128(0),
+            max: S::from_u128(0),
+        }
+    }
+
+    /// `value` is at most `max`.
+    pub fn write(&mut self, value: S, max: S) {
+        debug_assert!(value <= max);
+        self.max = self.max.try_push(&max, &max).expect(TOO_BIG);
+        self.index = self.index.try_push(&value, &max).unwrap();
+    }
+
+    pub fn finish(self) -> S {
+        self.index
+    }
+}
+
+/// Unpacks what a `Writer` packed, back to front.
+#[doc(hidden)]
+pub struct Reader<S> {
+    index: S,
+}
+
+impl<S: State> Reader<S> {
+    pub fn new(index: S) -> Self {
+        Self { index }
+    }
+
+    /// Reads the last value not read yet, which was at most `max`.
+    pub fn read(&mut self, max: &S) -> S {
+        self.index.pop(max)
+    }
+}
+
+/// How many values a sequence can take when its length is below `lengths` and each of its
+/// items is at most `max`: one for the empty sequence, plus `max + 1` for those of length 1,
+/// and so on. `None` if that doesn't fit.
+///
+/// This is how `#[encode(len = ..)]` counts, so that items past the length take no room.
+/// `sequences(len, max)` is also the index of the first sequence of length `len`.
+pub fn sequences<S: State>(lengths: u64, max: &S) -> Option<S> {
+    let mut total = S::from_u128(0);
+    for _ in 0..lengths {
+        // a sequence of every length so far, each followed by another item, or nothing
+        total = total.try_push(&S::from_u128(1), max)?;
+    }
+    Some(total)
+}
+
+/// The biggest index of a sequence, see `sequences`.
+#[doc(hidden)]
+pub fn sequences_max<S: State>(lengths: u64, max: Option<S>) -> Option<S> {
+    Some(sequences(lengths, &max?)?.sub(&S::from_u128(1)))
+}
+
+/// Writes a length and the sequence `items` holds, as packed by `sequences`.
+#[doc(hidden)]
+pub fn write_sequence<S: State>(
+    writer: &mut Writer<S>,
+    lengths: u64,
+    max: &S,
+    len: u64,
+    items: Writer<S>,
+) {
+    let index = fits(sequences(len, max)).try_add(&items.finish());
+    let max = fits(sequences_max(lengths, Some(max.clone())));
+    writer.write(fits(index), max);
+}
+
+/// Reads what `write_sequence` wrote, returns the length and a reader for the items.
+#[doc(hidden)]
+pub fn read_sequence<S: State>(reader: &mut Reader<S>, lengths: u64, max: &S) -> (u64, Reader<S>) {
+    let mut index = reader.read(&fits(sequences_max(lengths, Some(max.clone()))));
+    let mut len = 0;
+    // how many sequences there are of length `len`
+    let mut count = Some(S::from_u128(1));
+    while let Some(below) = count.filter(|count| index >= *count) {
+        index = index.sub(&below);
+        count = below.try_push(&S::from_u128(0), max);
+        len += 1;
+    }
+    (len, Reader::new(index))
+}
+
+/// `len` as an index into `array`, which holds `capacity` items and takes its length from
+/// `length`. Decoded lengths always fit thanks to `lengths`, but a value can hold anything
+/// before it's encoded.
+#[doc(hidden)]
+pub fn check_len(len: u64, capacity: usize, array: &str, length: &str) -> usize {
+    assert!(
+        len <= capacity as u64,
+        "`{length}` is {len}, but `{array}` only holds {capacity} items"
+    );
+    len as usize
+}
+
+/// How many lengths a field whose biggest index is `max` can hold, checked against the
+/// capacities of the arrays it's the length of.
+#[doc(hidden)]
+pub fn lengths(max: Option<u64>, capacities: &[usize]) -> u64 {
+    match max {
+        Some(max) if capacities.iter().all(|&capacity| max <= capacity as u64) => max + 1,
+        _ => panic!("the length can be longer than the array"),
+    }
+}
+
+/// Like `lengths`, at compile time where the length's cardinality is known by then.
+#[doc(hidden)]
+pub const fn assert_capacity(lengths: u128, capacity: usize) {
+    assert!(
+        lengths <= capacity as u128 + 1,
+        "the length can be longer than the array"
+    );
+}
+
+/// `index` in a sum, after the things whose biggest indices are `before`.
+#[doc(hidden)]
+pub fn offset<S: State>(before: impl IntoIterator<Item = Option<S>>, index: S) -> S {
+    fits(sum(before).and_then(|offset| offset.try_add(&index)))
+}
+
+/// `fields` as parts of something called `name`.
+#[doc(hidden)]
+pub fn nest(name: &str, fields: Vec<Field>) -> Vec<Field> {
+    let fields = fields.into_iter().map(|field| {
+        let separator = match field.name.starts_with('[') || field.name.is_empty() {
+            true => "",
+            false => ".",
+        };
+        Field {
+            name: format!("{name}{separator}{}", field.name),
+            ..field
+        }
+    });
+    fields.collect()
+}
+
+/// Integers, and arrays of them, under `#[encode(cardinality = ..)]`, where every integer
+/// is below the given bound.
+#[doc(hidden)]
+pub trait Bounded: Sized {
+    /// How many integers there are.
+    const COUNT: u32;
+
+    fn write_below<S: State>(&self, bound: u128, writer: &mut Writer<S>);
+
+    /// Reads back to front, like `Reader`.
+    fn read_below<S: State>(bound: u128, reader: &mut Reader<S>) -> Self;
+
+    fn fields_below(&self, bound: u128) -> Vec<Field>;
+}
+
+impl Encodable for () {
+    fn max_index<S: State>() -> Option<S> {
+        Some(S::from_u128(0))
+    }
+
+    fn to_index<S: State>(&self) -> S {
+        S::from_u128(0)
+    }
+
+    fn from_index<S: State>(_index: S) -> Self {}
+}
+
+impl Encodable for bool {
+    fn max_index<S: State>() -> Option<S> {
+        Some(S::from_u128(1))
+    }
+
+    fn to_index<S: State>(&self) -> S {
+        S::from_u128(*self as u128)
+    }
+
+    fn from_index<S: State>(index: S) -> Self {
+        index.to_u128() == 1
+    }
+}
+
+macro_rules! integers {
+    ($($int:ty),*) => {$(
+        impl Encodable for $int {
+            fn max_index<S: State>() -> Option<S> {
+                fitting(<$int>::MAX.into())
+            }
+
+            fn to_index<S: State>(&self) -> S {
+                assert_fits::<S, Self>();
+                S::from_u128(*self as u128)
+            }
+
+            fn from_index<S: State>(index: S) -> Self {
+                index.to_u128() as $int
+            }
+        }
+
+        impl Bounded for $int {
+            const COUNT: u32 = 1;
+
+            fn write_below<S: State>(&self, bound: u128, writer: &mut Writer<S>) {
+                writer.write(S::from_u128(*self as u128), fits(bound_max(bound)));
+            }
+
+            fn read_below<S: State>(bound: u128, reader: &mut Reader<S>) -> Self {
+                reader.read(&fits(bound_max(bound))).to_u128() as $int
+            }
+
+            fn fields_below(&self, bound: u128) -> Vec<Field> {
+                vec![Field {
+                    name: String::new(),
+                    value: *self as u128,
+                    cardinality: bound,
+                }]
+            }
+        }
+    )*};
+}
+
+integers!(u8, u16, u32, u64);
+
+impl<T: Encodable, const N: usize> Encodable for [T; N] {
+    fn max_index<S: State>() -> Option<S> {
+        power_max(T::max_index(), N as u32)
+    }
+
+    fn to_index<S: State>(&self) -> S {
+        let max = assert_fits::<S, T>();
+        let mut writer = Writer::new();
+        for item in self {
+            writer.write(item.to_index(), max.clone());
+        }
+        writer.finish()
+    }
+
+    fn from_index<S: State>(index: S) -> Self {
+        let max = assert_fits::<S, T>();
+        let mut reader = Reader::new(index);
+        let mut items: Self = std::array::from_fn(|_| T::from_index(reader.read(&max)));
+        items.reverse();
+        items
+    }
+
+    fn fields(&self) -> Vec<Field> {
+        let items = self.iter().enumerate();
+        items
+            .flat_map(|(i, item)| nest(&format!("[{i}]"), item.fields()))
+            .collect()
+    }
+}
+
+impl<T: Encodable> Encodable for Option<T> {
+    fn max_index<S: State>() -> Option<S> {
+        sum_max([Some(S::from_u128(0)), T::max_index()])
+    }
+
+    fn to_index<S: State>(&self) -> S {
+        assert_fits::<S, Self>();
+        match self {
+            None => S::from_u128(0),
+            Some(value) => offset([Some(S::from_u128(0))], value.to_index()),
+        }
+    }
+
+    fn from_index<S: State>(index: S) -> Self {
+        let none = S::from_u128(0);
+        (index > none).then(|| T::from_index(skip(index, &none)))
+    }
+
+    fn fields(&self) -> Vec<Field> {
+        let mut fields = vec![Field {
+            name: String::new(),
+            value: self.is_some() as u128,
+            cardinality: 2,
+        }];
+        if let Some(value) = self {
+            fields.extend(nest("Some", value.fields()));
+        }
+        fields
+    }
+}
+
+impl<T: Encodable, E: Encodable> Encodable for Result<T, E> {
+    fn max_index<S: State>() -> Option<S> {
+        sum_max([T::max_index(), E::max_index()])
+    }
+
+    fn to_index<S: State>(&self) -> S {
+        assert_fits::<S, Self>();
+        match self {
+            Ok(value) => value.to_index(),
+            Err(err) => offset([T::max_index()], err.to_index()),
+        }
+    }
+
+    fn from_index<S: State>(index: S) -> Self {
+        let max = assert_fits::<S, T>();
+        match index <= max {
+            true => Ok(T::from_index(index)),
+            false => Err(E::from_index(skip(index, &max))),
+        }
+    }
+
+    fn fields(&self) -> Vec<Field> {
+        let (value, name, fields) = match self {
+            Ok(value) => (0, "Ok", value.fields()),
+            Err(err) => (1, "Err", err.fields()),
+        };
+        let tag = Field {
+            name: String::new(),
+            value,
+            cardinality: 2,
+        };
+        std::iter::once(tag).chain(nest(name, fields)).collect()
+    }
+}
+
+impl<T: Bounded, const N: usize> Bounded for [T; N] {
+    const COUNT: u32 = T::COUNT * N as u32;
+
+    fn write_below<S: State>(&self, bound: u128, writer: &mut Writer<S>) {
+        for item in self {
+            item.write_below(bound, writer);
+        }
+    }
+
+    fn read_below<S: State>(bound: u128, reader: &mut Reader<S>) -> Self {
+        let mut items: Self = std::array::from_fn(|_| T::read_below(bound, reader));
+        items.reverse();
+        items
+    }
+
+    fn fields_below(&self, bound: u128) -> Vec<Field> {
+        let items = self.iter().enumerate();
+        items
+            .flat_map(|(i, item)| nest(&format!("[{i}]"), item.fields_below(bound)))
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -70,7 +961,7 @@ mod tests {
 
     #[test]
     fn store_no_data() {
-        let mut state = 0;
+        let mut state = 0u64;
 
         let unit = 0;
         let cardinality = 1; // 1 possible state constitutes no data
@@ -84,7 +975,7 @@ mod tests {
         let cardinality = 2;
 
         for bit in [0, 1] {
-            let mut state = 0;
+            let mut state = 0u64;
             push(&mut state, bit, cardinality);
             assert_eq!(pop(&mut state, cardinality), bit);
         }
@@ -95,7 +986,7 @@ mod tests {
         let cardinality = 3; // {0, 1, 2}
 
         for value in [0, 1, 2] {
-            let mut state = 0;
+            let mut state = 0u64;
             push(&mut state, value, cardinality);
             assert_eq!(pop(&mut state, cardinality), value);
         }
@@ -106,7 +997,7 @@ mod tests {
         let cardinality = 2;
 
         for (a, b) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
-            let mut state = 0;
+            let mut state = 0u64;
             push(&mut state, a, cardinality);
             push(&mut state, b, cardinality);
             assert_eq!(pop(&mut state, cardinality), b);
@@ -120,9 +1011,16 @@ mod tests {
             let just_data = data_vs_cardinalities.map(|(data, _)| data);
             let just_possibilities = data_vs_cardinalities.map(|(_, possibilities)| possibilities);
 
-            let state = encode(&just_data, &just_possibilities);
+            let state: u64 = encode(&just_data, &just_possibilities);
             let decoded = decode(state, &just_possibilities);
             assert_eq!(decoded, just_data);
+
+            // anything that fits in a u64 fits in these
+            let wide: u128 = encode(&just_data, &just_possibilities);
+            assert_eq!(wide, state.into());
+            let var: Varuint = encode(&just_data, &just_possibilities);
+            assert_eq!(var.to_u128(), state.into());
+            assert_eq!(decode(var, &just_possibilities), just_data);
         }
 
         check(&[
@@ -150,5 +1048,361 @@ mod tests {
         check(&[(u16::MAX.into(), Into::<u64>::into(u16::MAX) + 1); 4]);
         check(&[(u32::MAX.into(), Into::<u64>::into(u32::MAX) + 1); 2]);
     }
-}
 
+    #[derive(Encodable, Copy, Clone, Debug, PartialEq)]
+    enum Suit {
+        Hearts,
+        Spades,
+        Clubs,
+    }
+
+    #[derive(Encodable, Debug, PartialEq)]
+    struct Hand {
+        suits: [Suit; 2],
+        #[encode(cardinality = 13)]
+        ranks: [u8; 2],
+        revealed: bool,
+        bet: u16,
+    }
+
+    #[derive(Encodable, Debug, PartialEq)]
+    struct Table(Hand, #[encode(cardinality = 5)] u32);
+
+    #[test]
+    fn derived() {
+        assert_eq!(Suit::cardinality::<u64>(), Some(3));
+        assert_eq!(Suit::Clubs.to_index::<u64>(), 2);
+        assert_eq!(Suit::from_index(1u64), Suit::Spades);
+
+        assert_eq!(
+            Hand::cardinality::<u64>(),
+            Some(3 * 3 * 13 * 13 * 2 * 65536)
+        );
+        let hand = Hand {
+            suits: [Suit::Clubs, Suit::Hearts],
+            ranks: [12, 3],
+            revealed: true,
+            bet: 500,
+        };
+        // the same as packing the fields by hand
+        let index: u64 = encode(&[2, 0, 12, 3, 1, 500], &[3, 3, 13, 13, 2, 65536]);
+        assert_eq!(hand.to_index::<u64>(), index);
+        assert_eq!(Hand::from_index(index), hand);
+
+        let table = Table(hand, 4);
+        let index: u64 = table.to_index();
+        assert_eq!(
+            Table::cardinality::<u64>(),
+            Hand::cardinality::<u64>().map(|hands| hands * 5)
+        );
+        assert_eq!(Table::from_index(index), table);
+
+        let names: Vec<_> = table.fields().into_iter().map(|f| f.name).collect();
+        assert_eq!(
+            names,
+            [
+                "0.suits[0]",
+                "0.suits[1]",
+                "0.ranks[0]",
+                "0.ranks[1]",
+                "0.revealed",
+                "0.bet",
+                "1",
+            ]
+        );
+        let field = &table.fields()[2];
+        assert_eq!((field.value, field.cardinality), (12, 13));
+    }
+
+    #[derive(Encodable, Debug, PartialEq)]
+    enum Game {
+        Menu {
+            #[encode(cardinality = 3)]
+            cursor: u8,
+        },
+        Playing(Suit, bool),
+        Won,
+    }
+
+    #[test]
+    fn sums() {
+        assert_eq!(Option::<Suit>::cardinality::<u64>(), Some(4));
+        assert_eq!(None::<Suit>.to_index::<u64>(), 0);
+        assert_eq!(Some(Suit::Clubs).to_index::<u64>(), 3);
+        assert_eq!(Option::<Suit>::from_index(1u64), Some(Suit::Hearts));
+
+        type Either = Result<Suit, bool>;
+        assert_eq!(Either::cardinality::<u64>(), Some(5));
+        assert_eq!(Err::<Suit, _>(true).to_index::<u64>(), 4);
+        assert_eq!(Either::from_index(2u64), Ok(Suit::Clubs));
+        assert_eq!(Either::from_index(3u64), Err(false));
+
+        // 3 menu states, then 3 * 2 playing states, then 1 won state
+        assert_eq!(Game::cardinality::<u64>(), Some(3 + 6 + 1));
+        let all = [
+            Game::Menu { cursor: 0 },
+            Game::Menu { cursor: 2 },
+            Game::Playing(Suit::Hearts, false),
+            Game::Playing(Suit::Clubs, true),
+            Game::Won,
+        ];
+        let indices = all.map(|game| {
+            let index: u64 = game.to_index();
+            assert_eq!(Game::from_index(index), game);
+            index
+        });
+        assert_eq!(indices, [0, 2, 3, 8, 9]);
+
+        let fields = Game::Playing(Suit::Spades, true).fields();
+        let fields: Vec<_> = fields.iter().map(|f| (&*f.name, f.value)).collect();
+        assert_eq!(fields, [("", 1), ("Playing.0", 1), ("Playing.1", 1)]);
+        let nested = Some(Game::Won).fields();
+        let names: Vec<_> = nested.iter().map(|f| &*f.name).collect();
+        assert_eq!(names, ["", "Some"]);
+    }
+
+    #[derive(Encodable, Debug, PartialEq)]
+    struct Deck {
+        #[encode(len = count)]
+        suits: [Suit; 4],
+        #[encode(cardinality = 3)]
+        count: u8,
+        #[encode(len = count, cardinality = 13)]
+        ranks: [u8; 4],
+    }
+
+    #[derive(Encodable, Debug, PartialEq)]
+    enum Stack {
+        Open(#[encode(cardinality = 3)] u8, #[encode(len = 0)] [bool; 2]),
+        Closed,
+    }
+
+    #[test]
+    fn lengths() {
+        assert_eq!(sequences(0, &4u64), Some(0));
+        assert_eq!(sequences(1, &4u64), Some(1));
+        assert_eq!(sequences(4, &1u64), Some(1 + 2 + 4 + 8));
+        assert_eq!(sequences(65, &1u128), Some((1 << 65) - 1));
+        assert_eq!(sequences(65, &1u64), None);
+
+        // every index is a different deck
+        assert_eq!(Deck::cardinality::<u64>(), Some(1 + 39 + 39 * 39));
+        for index in 0..Deck::cardinality::<u64>().unwrap() {
+            assert_eq!(Deck::from_index(index).to_index::<u64>(), index);
+        }
+        let deck = Deck {
+            suits: [Suit::Clubs, Suit::Hearts, Suit::Hearts, Suit::Hearts],
+            count: 1,
+            ranks: [5, 0, 0, 0],
+        };
+        assert_eq!(deck.to_index::<u64>(), 1 + 2 * 13 + 5);
+        assert_eq!(Deck::from_index(32u64), deck);
+        // what's past the length is forgotten
+        let messy = Deck {
+            ranks: [5, 6, 7, 8],
+            ..deck
+        };
+        assert_eq!(Deck::from_index(messy.to_index::<u64>()), deck);
+
+        let names: Vec<_> = deck.fields().into_iter().map(|f| f.name).collect();
+        assert_eq!(names, ["suits[0]", "count", "ranks[0]"]);
+
+        assert_eq!(Stack::cardinality::<u64>(), Some(1 + 2 + 4 + 1));
+        for index in 0..Stack::cardinality::<u64>().unwrap() {
+            assert_eq!(Stack::from_index(index).to_index::<u64>(), index);
+        }
+        assert_eq!(Stack::Open(2, [true, false]).to_index::<u64>(), 1 + 2 + 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "`count` is 5, but `suits` only holds 4 items")]
+    fn too_long() {
+        let deck = Deck {
+            suits: [Suit::Hearts; 4],
+            count: 5,
+            ranks: [0; 4],
+        };
+        deck.to_index::<u64>();
+    }
+
+    #[derive(Encodable, Debug, PartialEq)]
+    struct Packet {
+        id: u64,
+        #[encode(cardinality = 1000)]
+        port: u16,
+        reply: Option<u32>,
+    }
+
+    #[test]
+    fn wide_states() {
+        let packet = Packet {
+            id: u64::MAX,
+            port: 999,
+            reply: Some(7),
+        };
+        assert_eq!(
+            Packet::cardinality::<u128>(),
+            Some((1 << 64) * 1000 * ((1 << 32) + 1))
+        );
+        let wide: u128 = packet.to_index();
+        assert_eq!(Packet::from_index(wide), packet);
+
+        let var: Varuint = packet.to_index();
+        assert_eq!(var.to_u128(), wide);
+        let bytes = var.to_bytes();
+        assert_eq!(bytes.len(), var.bits().div_ceil(7) as usize);
+        assert_eq!(
+            Varuint::from_bytes(&bytes),
+            Some((var.clone(), bytes.len()))
+        );
+        assert_eq!(Packet::from_index(var), packet);
+
+        // narrow ones work as long as everything fits
+        let small: u32 = encode(&[3, 4], &[1 << 16, 1 << 16]);
+        assert_eq!(small, 3 << 16 | 4);
+        assert_eq!(decode(small, &[1 << 16, 1 << 16]), [3, 4]);
+        assert!(too_big::<u32>(&[1 << 16, (1 << 16) + 1]));
+        assert!(!too_big::<u128>(&[u64::MAX, u64::MAX]));
+        assert!(!too_big::<Varuint>(&[u64::MAX; 10]));
+    }
+
+    #[derive(Encodable, Debug, PartialEq)]
+    struct Log {
+        packets: [Packet; 2],
+        #[encode(cardinality = 3)]
+        kept: u8,
+        #[encode(len = kept)]
+        ids: [u64; 2],
+        last: Result<Packet, [u64; 2]>,
+    }
+
+    #[test]
+    fn bigger_than_u128() {
+        assert_eq!(Log::max_index::<u128>(), None);
+        assert_eq!(Log::try_from_index(0u128), Err(Error::Overflow));
+        let packet = |id| Packet {
+            id,
+            port: 80,
+            reply: None,
+        };
+        let log = Log {
+            packets: [packet(u64::MAX), packet(3)],
+            kept: 2,
+            ids: [u64::MAX, 5],
+            last: Err([7, u64::MAX]),
+        };
+        let index: Varuint = log.to_index();
+        assert!(index.bits() > 256);
+        assert_eq!(Log::from_index(index), log);
+
+        let max: Varuint = Log::max_index().unwrap();
+        assert_eq!(Log::from_index(max.clone()).to_index::<Varuint>(), max);
+        let past = Log::cardinality::<Varuint>().unwrap();
+        assert_eq!(Log::try_from_index(past), Err(Error::StateOutOfRange));
+
+        // the same as packing the fields by hand
+        let data = [1u32, u32::MAX, 0, 7, 9];
+        let index: Varuint = encode(&data.map(u64::from), &[1 << 32; 5]);
+        assert_eq!(data.to_index::<Varuint>(), index);
+        assert_eq!(<[u32; 5]>::from_index(index), data);
+    }
+
+    #[test]
+    #[should_panic(expected = "too many values to fit in the state")]
+    fn too_small() {
+        [0u64; 3].to_index::<u128>();
+    }
+
+    #[test]
+    fn varuint() {
+        let zero = Varuint::default();
+        assert_eq!((zero.bits(), zero.to_bytes()), (0, vec![0]));
+        assert_eq!(Varuint::from_u128(300).to_bytes(), [0xac, 0x02]);
+        assert_eq!(
+            Varuint::from_bytes(&[0xac, 0x02, 0xff]),
+            Some((Varuint::from_u128(300), 2))
+        );
+        assert_eq!(Varuint::from_bytes(&[0xac]), None);
+
+        let big = Varuint::from_u128(u128::MAX);
+        assert!(big > Varuint::from_u128(1 << 64));
+        let mut packed = big.try_push(&Varuint::from_u128(5), &big).unwrap();
+        assert_eq!(packed.bits(), 256);
+        assert_eq!(packed.pop(&big), Varuint::from_u128(5));
+        assert_eq!(packed, big);
+        assert_eq!(big.sub(&big), zero);
+        // a value as wide as the state leaves no room for anything before it
+        assert_eq!(0u64.try_push(&7, &u64::MAX), Some(7));
+        assert_eq!(1u64.try_push(&0, &u64::MAX), None);
+
+        // bigger than any primitive
+        let cardinalities = [u64::MAX; 5];
+        let data = [1, u64::MAX - 1, 0, 12345, u64::MAX - 2];
+        let state: Varuint = encode(&data, &cardinalities);
+        assert!(state.bits() > 256);
+        let (read, _) = Varuint::from_bytes(&state.to_bytes()).unwrap();
+        assert_eq!(decode(read, &cardinalities), data);
+    }
+
+    #[test]
+    fn checked() {
+        let cardinalities = [3, 5];
+        assert_eq!(try_encode::<u64, 2>(&[2, 4], &cardinalities), Ok(14));
+        assert_eq!(
+            try_encode::<u64, 2>(&[2, 5], &cardinalities),
+            Err(Error::ValueOutOfRange(1))
+        );
+        assert_eq!(
+            try_encode::<u64, 2>(&[0, 0], &[3, 0]),
+            Err(Error::ZeroCardinality(1))
+        );
+        // even next to ones that are too big together, and without underflowing
+        assert!(!too_big::<u32>(&[5, 0, u64::MAX, u64::MAX]));
+        assert_eq!(
+            try_decode(0u32, &[5, 0, u64::MAX, u64::MAX]),
+            Err(Error::ZeroCardinality(1))
+        );
+        assert_eq!(
+            try_encode::<u32, 2>(&[0, 0], &[1 << 16, (1 << 16) + 1]),
+            Err(Error::Overflow)
+        );
+
+        assert_eq!(try_decode(14u64, &cardinalities), Ok([2, 4]));
+        assert_eq!(
+            try_decode(15u64, &cardinalities),
+            Err(Error::StateOutOfRange)
+        );
+        assert_eq!(
+            try_decode(u64::MAX, &[u64::MAX]),
+            Err(Error::StateOutOfRange)
+        );
+        assert_eq!(try_decode(0u32, &[u64::MAX]), Err(Error::Overflow));
+        let huge: Varuint = encode(&[1, 0, 0], &[u64::MAX; 3]);
+        assert_eq!(try_decode(huge.clone(), &[u64::MAX; 3]), Ok([1, 0, 0]));
+        assert_eq!(
+            try_decode(huge, &[u64::MAX; 2]),
+            Err(Error::StateOutOfRange)
+        );
+
+        // a pasted code for a deck that can't exist
+        assert_eq!(Deck::try_from_index(32u64), Ok(Deck::from_index(32u64)));
+        let past = Deck::cardinality::<u64>().unwrap();
+        assert_eq!(Deck::try_from_index(past), Err(Error::StateOutOfRange));
+        let var = Varuint::from_bytes(&[0xff; 20]).map(|(var, _)| var);
+        assert_eq!(var, None);
+        let (var, _) = Varuint::from_bytes(&[[0xff; 20].as_slice(), &[1]].concat()).unwrap();
+        assert_eq!(Packet::try_from_index(var), Err(Error::StateOutOfRange));
+    }
+
+    #[test]
+    fn full_width() {
+        assert_eq!(u64::max_index::<u64>(), Some(u64::MAX));
+        assert_eq!(u64::cardinality::<u64>(), None);
+        assert_eq!(u64::cardinality::<u128>(), Some(1 << 64));
+        assert_eq!(u64::from_index(u64::MAX.to_index::<u64>()), u64::MAX);
+        assert_eq!(<[u32; 2]>::from_index(u64::MAX), [u32::MAX; 2]);
+        assert_eq!([1u32, 2].to_index::<u64>(), 1 << 32 | 2);
+        let field = &u64::MAX.fields()[0];
+        assert_eq!((field.value, field.cardinality), (u64::MAX.into(), 1 << 64));
+    }
+}
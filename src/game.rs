@@ -1,120 +1,293 @@
 // Copied from https://github.com/zesterer/bitwise-examples/blob/b8479bfd485356b3ac399f984e713939ba6a6573/src/lib.rs
 // and modified to support input keypress events instead of just is_key_down
 
-use minifb::{KeyRepeat, Window, WindowOptions};
-use std::{marker::PhantomData, time::Duration};
+use std::{io, marker::PhantomData};
 
-pub use minifb::Key;
+pub use action::Action;
+pub use color::{Blend, Color};
+pub use held::Held;
+pub use key::{Key, KeyRepeat};
+pub use mouse::MouseButton;
+pub use output::Output;
+
+pub mod action;
+pub mod backend;
+pub mod color;
+pub mod font;
+pub mod golden;
+pub mod headless;
+mod held;
+mod history;
+pub mod image;
+mod key;
+mod mouse;
+pub mod options;
+mod output;
+pub mod recording;
+pub mod runner;
+pub mod saves;
+#[cfg(test)]
+mod test_games;
+
+use crate::cheeky_encoding::Field;
+use backend::{Backend, WindowOptions};
+use runner::Runner;
 
 pub trait Game: Sized + 'static {
     const NAME: &'static str;
     const WIDTH: usize;
     const HEIGHT: usize;
 
-    fn init() -> u64;
+    /// Ticks per second, at least one. The runner keeps to this regardless of how fast the
+    /// backend can show frames, so game speed doesn't depend on the machine.
+    const TICK_RATE: u32 = 60;
 
-    fn tick(prev: u64, input: &Input<'_, Self>, output: &mut Output<'_, Self>) -> u64;
+    /// Set this to have the runner press held keys again and again, like typing does. Repeats
+    /// show up as ordinary presses.
+    const KEY_REPEAT: Option<KeyRepeat> = None;
 
-    fn run() -> ! {
-        let mut win = Window::new(
-            Self::NAME,
-            Self::WIDTH,
-            Self::HEIGHT,
-            WindowOptions::default(),
-        )
-        .unwrap();
+    /// What the player can do, for games that would rather not hardcode keys. The runner turns
+    /// key events into action events using these defaults or the player's own bindings (see
+    /// `action`). Actions are referred to by their index here.
+    const ACTIONS: &'static [Action] = &[];
 
-        win.limit_update_rate(Some(Duration::from_micros(16600)));
+    /// Whether `run` has the runner's debugging hotkeys (see `runner::Runner`). They keep F1 to
+    /// F12 for themselves, so a game that wants those keys turns this off.
+    const HOTKEYS: bool = true;
 
-        let mut state = Self::init();
+    fn init() -> u64;
 
-        let mut tick = 0;
-        while win.is_open() && !win.is_key_down(Key::Escape) {
-            let mut buf = vec![0; Self::WIDTH * Self::HEIGHT];
+    fn tick(prev: u64, input: &Input<'_, Self>, output: &mut Output<'_, Self>) -> u64;
 
-            let input = Input {
-                win: &win,
-                tick,
-                phantom: PhantomData,
-            };
-            let mut output = Output::new();
+    /// How big a window to open, and how it behaves. `--scale` and `--fullscreen` override it.
+    fn window_options() -> WindowOptions {
+        WindowOptions::default()
+    }
 
-            state = Self::tick(state, &input, &mut output);
+    /// Split a state into named parts for the runner's debug overlay. Games that don't only get
+    /// the raw bits shown.
+    fn debug_fields(_state: u64) -> Vec<Field> {
+        Vec::new()
+    }
 
-            output.write_to(&mut buf);
+    /// Play in a window or the terminal, configured from the command line (see `Options`).
+    fn run() -> ! {
+        let options = options::Options::from_args();
+        let runner = Runner::<Self>::from_options(&options).unwrap_or_else(|err| {
+            eprintln!("{err}");
+            std::process::exit(1)
+        });
+        match options.backend {
+            #[cfg(feature = "minifb")]
+            options::BackendKind::Window => {
+                let mut window = Self::window_options();
+                if let Some(scale) = options.scale {
+                    window.scale = scale;
+                }
+                window.fullscreen |= options.fullscreen;
+                let mut backend =
+                    backend::minifb::Minifb::new(Self::NAME, Self::WIDTH, Self::HEIGHT, &window);
+                runner.run(&mut backend);
+            }
+            options::BackendKind::Terminal => {
+                // dropped before exiting, which puts the terminal back the way it was
+                let mut backend = backend::terminal::Terminal::new().unwrap_or_else(|err| {
+                    eprintln!("can't use this terminal: {err}");
+                    std::process::exit(1)
+                });
+                runner.run(&mut backend);
+            }
+        }
+        std::process::exit(0)
+    }
 
-            win.update_with_buffer(&buf, Self::WIDTH, Self::HEIGHT)
-                .unwrap();
+    /// Tick until the backend closes, returns the final state. There are no hotkeys, every key
+    /// reaches the game.
+    fn run_on(backend: &mut impl Backend, state: u64) -> u64 {
+        Runner::<Self>::new(state).run(backend)
+    }
 
-            tick += 1;
-        }
+    /// Advance the game by one tick and rasterize whatever it drew. `held` is what was held
+    /// before `events`.
+    fn step(state: u64, tick: u64, held: &Held, events: &[Event]) -> (u64, Frame) {
+        let input = Input::with_held(tick, held, events);
+        let mut output = Output::new();
 
-        std::process::exit(0)
+        let state = Self::tick(state, &input, &mut output);
+
+        let mut frame = Frame::new(Self::WIDTH, Self::HEIGHT);
+        output.write_to(&mut frame.pixels);
+        (state, frame)
     }
 }
 
+/// Something that happened during a tick.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    KeyPressed(Key),
+    KeyReleased(Key),
+    /// The mouse is now at this game pixel.
+    MouseMoved {
+        x: i32,
+        y: i32,
+    },
+    MousePressed(MouseButton),
+    MouseReleased(MouseButton),
+    /// A character was typed, after shift and keyboard layout. Never a control character,
+    /// those are keys.
+    Char(char),
+    /// An index into `Game::ACTIONS`.
+    ActionPressed(u8),
+    ActionReleased(u8),
+    /// Whole notches of the wheel, positive is right and down.
+    Scrolled {
+        x: i32,
+        y: i32,
+    },
+}
+
+/// Something typed, see `Input::get_typed`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Typed {
+    Char(char),
+    Backspace,
+    Enter,
+}
+
 pub struct Input<'a, G: Game> {
-    win: &'a Window,
+    events: &'a [Event],
     tick: u64,
+    /// As of the end of the tick, after `events`.
+    held: Held,
     phantom: PhantomData<&'static mut G>,
 }
 
 impl<'a, G: Game> Input<'a, G> {
+    /// Input doesn't need a window, any source of events will do.
+    pub fn new(tick: u64, events: &'a [Event]) -> Self {
+        Self::with_held(tick, &Held::default(), events)
+    }
+
+    /// Input for a tick that started with `held` held.
+    pub fn with_held(tick: u64, held: &Held, events: &'a [Event]) -> Self {
+        let mut held = *held;
+        held.apply(events);
+        Self {
+            events,
+            tick,
+            held,
+            phantom: PhantomData,
+        }
+    }
+
     pub fn tick(&self) -> u64 {
         self.tick
     }
 
-    pub fn get_keys_pressed(&self) -> impl Iterator<Item = Key> {
-        self.win.get_keys_pressed(KeyRepeat::No).into_iter()
+    pub fn get_keys_pressed(&self) -> impl Iterator<Item = Key> + '_ {
+        self.events.iter().filter_map(|event| match event {
+            Event::KeyPressed(key) => Some(*key),
+            _ => None,
+        })
     }
-}
 
-pub struct Output<'a, G: Game> {
-    shapes: Vec<Shape>,
-    phantom: PhantomData<&'a mut G>,
-}
+    pub fn is_key_down(&self, key: Key) -> bool {
+        self.held.is_key_down(key)
+    }
 
-enum Shape {
-    Rect {
-        x: i32,
-        y: i32,
-        w: u32,
-        h: u32,
-        color: [u8; 3],
-    },
-}
+    pub fn get_keys_released(&self) -> impl Iterator<Item = Key> + '_ {
+        self.events.iter().filter_map(|event| match event {
+            Event::KeyReleased(key) => Some(*key),
+            _ => None,
+        })
+    }
+
+    /// What was typed this tick, in order, for name entry and the like. Backspace and Enter
+    /// come from their keys (and repeat like them), everything else is `Event::Char`.
+    pub fn get_typed(&self) -> impl Iterator<Item = Typed> + '_ {
+        self.events.iter().filter_map(|event| match event {
+            Event::Char(c) => Some(Typed::Char(*c)),
+            Event::KeyPressed(Key::Backspace) => Some(Typed::Backspace),
+            Event::KeyPressed(Key::Enter | Key::NumPadEnter) => Some(Typed::Enter),
+            _ => None,
+        })
+    }
+
+    /// `action` indexes `Game::ACTIONS`.
+    pub fn is_action_down(&self, action: usize) -> bool {
+        self.held.is_action_down(action)
+    }
+
+    pub fn get_actions_pressed(&self) -> impl Iterator<Item = usize> + '_ {
+        self.events.iter().filter_map(|event| match event {
+            Event::ActionPressed(action) => Some(*action as usize),
+            _ => None,
+        })
+    }
+
+    pub fn get_actions_released(&self) -> impl Iterator<Item = usize> + '_ {
+        self.events.iter().filter_map(|event| match event {
+            Event::ActionReleased(action) => Some(*action as usize),
+            _ => None,
+        })
+    }
+
+    /// Where the mouse is in game pixels, possibly off screen. `None` until it first moves.
+    pub fn mouse_position(&self) -> Option<[i32; 2]> {
+        self.held.mouse
+    }
+
+    pub fn is_mouse_down(&self, button: MouseButton) -> bool {
+        self.held.is_mouse_down(button)
+    }
 
-impl<'a, G: Game> Output<'a, G> {
-    pub fn rect(&mut self, x: i32, y: i32, w: u32, h: u32, color: [u8; 3]) {
-        self.shapes.push(Shape::Rect { x, y, w, h, color });
+    pub fn get_mouse_pressed(&self) -> impl Iterator<Item = MouseButton> + '_ {
+        self.events.iter().filter_map(|event| match event {
+            Event::MousePressed(button) => Some(*button),
+            _ => None,
+        })
     }
 
-    fn new() -> Self {
+    pub fn get_mouse_released(&self) -> impl Iterator<Item = MouseButton> + '_ {
+        self.events.iter().filter_map(|event| match event {
+            Event::MouseReleased(button) => Some(*button),
+            _ => None,
+        })
+    }
+
+    /// Notches scrolled this tick, `[right, down]`.
+    pub fn scroll(&self) -> [i32; 2] {
+        self.events
+            .iter()
+            .fold([0, 0], |[sx, sy], event| match event {
+                Event::Scrolled { x, y } => [sx + x, sy + y],
+                _ => [sx, sy],
+            })
+    }
+}
+/// A rasterized `Output`, one `u32` per pixel in the same format minifb expects.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u32>,
+}
+
+impl Frame {
+    pub fn new(width: usize, height: usize) -> Self {
         Self {
-            shapes: Vec::new(),
-            phantom: PhantomData,
+            width,
+            height,
+            pixels: vec![0; width * height],
         }
     }
 
-    fn write_to(self, buf: &mut Vec<u32>) {
-        for shape in self.shapes {
-            match shape {
-                Shape::Rect { x, y, w, h, color } => {
-                    for j in 0..h {
-                        for i in 0..w {
-                            let pos = [x + i as i32, y + j as i32];
-                            if pos[0] > 0
-                                && pos[0] < G::WIDTH as i32
-                                && pos[1] > 0
-                                && pos[1] < G::HEIGHT as i32
-                            {
-                                buf[pos[1] as usize * G::WIDTH + pos[0] as usize] =
-                                    u32::from_le_bytes([color[0], color[1], color[2], 255]);
-                            }
-                        }
-                    }
-                }
-            }
-        }
+    pub fn get(&self, x: usize, y: usize) -> u32 {
+        self.pixels[y * self.width + x]
     }
 }
+
+/// The error for files that don't parse.
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
@@ -0,0 +1,187 @@
+//! Abstract actions and the keys bound to them.
+//!
+//! A game declares its actions in `Game::ACTIONS`, each with default keys. Players can rebind
+//! them with a bindings file without the game knowing:
+//!
+//! ```text
+//! # lines replace an action's default keys, actions not mentioned keep theirs
+//! Up = W Up
+//! Confirm = Enter Space
+//! Pause =
+//! ```
+//!
+//! Key names are those of `Key::name`, in any case. Unless `--bindings` says otherwise the
+//! runner looks for `<name>.bindings` next to the game's saves (see `saves`).
+//!
+//! F1 to F12 can be bound, but while the runner's hotkeys are on (see `Game::HOTKEYS`) they
+//! never get this far.
+//!
+//! Recordings keep the action events themselves, so a replay doesn't depend on anyone's
+//! bindings. Only recordings from before actions existed get theirs from the bindings of
+//! whoever replays them.
+
+use std::{fs, io, path::Path};
+
+use super::{Event, Held, Key, held::MAX_ACTIONS, invalid};
+
+/// Something the player can do. The game refers to it by its index in `Game::ACTIONS`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Action {
+    /// How bindings files refer to it.
+    pub name: &'static str,
+    pub keys: &'static [Key],
+}
+
+/// Which keys trigger which action.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bindings {
+    /// One bit per `Key`, one mask per action.
+    keys: Vec<u128>,
+}
+
+impl Bindings {
+    /// The defaults of `actions`. The runner checks at compile time that a game's fit.
+    pub fn new(actions: &[Action]) -> Self {
+        assert!(
+            actions.len() <= MAX_ACTIONS,
+            "at most 64 actions fit in `Held`"
+        );
+        let keys = actions
+            .iter()
+            .map(|action| {
+                action
+                    .keys
+                    .iter()
+                    .fold(0, |mask, &key| mask | 1 << key as u8)
+            })
+            .collect();
+        Self { keys }
+    }
+
+    pub fn load(path: impl AsRef<Path>, actions: &[Action]) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        Self::parse(&text, actions).map_err(invalid)
+    }
+
+    /// The defaults of `actions`, with whatever `text` rebinds.
+    pub fn parse(text: &str, actions: &[Action]) -> Result<Self, String> {
+        let mut bindings = Self::new(actions);
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let bad = |what| format!("line {}: {what}", number + 1);
+
+            let (name, keys) = line
+                .split_once('=')
+                .ok_or_else(|| bad(format!("expected `action = keys`, found {line:?}")))?;
+            let name = name.trim();
+            let index = actions
+                .iter()
+                .position(|action| action.name.eq_ignore_ascii_case(name))
+                .ok_or_else(|| bad(format!("no action called {name:?}")))?;
+            bindings.keys[index] = keys.split_whitespace().try_fold(0, |mask, name| {
+                let key =
+                    Key::from_name(name).ok_or_else(|| bad(format!("no key called {name:?}")))?;
+                Ok::<_, String>(mask | 1 << key as u8)
+            })?;
+        }
+        Ok(bindings)
+    }
+
+    /// `events` with action presses and releases added after the key events that cause them.
+    /// `held` is what was held before `events`.
+    ///
+    /// Every press of a bound key presses its action, repeats included, but the action is only
+    /// released once none of its keys are down.
+    pub fn translate(&self, held: &Held, events: &[Event]) -> Vec<Event> {
+        let mut held = *held;
+        let mut out = Vec::with_capacity(events.len());
+        for &event in events {
+            out.push(event);
+            held.apply(&[event]);
+            let (key, pressed) = match event {
+                Event::KeyPressed(key) => (key, true),
+                Event::KeyReleased(key) => (key, false),
+                _ => continue,
+            };
+            for (index, &mask) in self.keys.iter().enumerate() {
+                let action = index as u8;
+                if mask & 1 << key as u8 == 0 {
+                    continue;
+                }
+                let translated = if pressed {
+                    Event::ActionPressed(action)
+                } else if held.keys & mask == 0 && held.is_action_down(action.into()) {
+                    Event::ActionReleased(action)
+                } else {
+                    continue;
+                };
+                held.apply(&[translated]);
+                out.push(translated);
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACTIONS: &[Action] = &[
+        Action {
+            name: "Jump",
+            keys: &[Key::Space, Key::Up],
+        },
+        Action {
+            name: "Duck",
+            keys: &[Key::Down],
+        },
+    ];
+
+    #[test]
+    fn translate() {
+        let bindings = Bindings::new(ACTIONS);
+        let events = bindings.translate(
+            &Held::default(),
+            &[
+                Event::KeyPressed(Key::Space),
+                Event::KeyPressed(Key::Up),
+                Event::KeyReleased(Key::Space),
+                Event::KeyPressed(Key::A),
+                Event::KeyReleased(Key::Up),
+                Event::KeyReleased(Key::Down),
+            ],
+        );
+        assert_eq!(
+            events,
+            [
+                Event::KeyPressed(Key::Space),
+                Event::ActionPressed(0),
+                Event::KeyPressed(Key::Up),
+                Event::ActionPressed(0),
+                Event::KeyReleased(Key::Space),
+                Event::KeyPressed(Key::A),
+                Event::KeyReleased(Key::Up),
+                Event::ActionReleased(0),
+                // it was never pressed, as far as this tick knows
+                Event::KeyReleased(Key::Down),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse() {
+        let bindings = Bindings::parse("# vim\n jump = k SPACE\n\nDuck=\n", ACTIONS).unwrap();
+        assert_eq!(
+            bindings.keys,
+            [1 << Key::K as u8 | 1 << Key::Space as u8, 0]
+        );
+
+        assert!(Bindings::parse("Fly = F", ACTIONS).is_err());
+        assert!(Bindings::parse("Jump = Hyper", ACTIONS).is_err());
+        assert!(Bindings::parse("Jump", ACTIONS).is_err());
+    }
+}
@@ -0,0 +1,58 @@
+//! Where events come from and where frames go.
+//!
+//! `Game::run` only talks to a `Backend`, so a game doesn't care whether it ends up in a
+//! window, a terminal or a test.
+
+use super::{Event, Frame};
+
+#[cfg(feature = "minifb")]
+pub mod minifb;
+pub mod terminal;
+
+/// How a game would like its window, see `Game::window_options`. Backends without windows
+/// ignore it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct WindowOptions {
+    /// Every game pixel starts out as a `scale` by `scale` square.
+    pub scale: u32,
+    /// Resizing stretches the game to fit and letterboxes the rest. Game coordinates, mouse
+    /// included, stay the same.
+    pub resizable: bool,
+    pub fullscreen: bool,
+}
+
+impl Default for WindowOptions {
+    fn default() -> Self {
+        Self {
+            scale: 1,
+            resizable: true,
+            fullscreen: false,
+        }
+    }
+}
+
+pub trait Backend {
+    /// False once the player has asked to quit.
+    fn is_open(&self) -> bool;
+
+    /// Events that happened since the last call. Called once per tick.
+    fn poll(&mut self) -> Vec<Event>;
+
+    /// Show a frame to the player.
+    fn present(&mut self, frame: &Frame);
+
+    /// Switch between fullscreen and a window, if that means anything to the backend.
+    fn toggle_fullscreen(&mut self) {}
+
+    /// Something for the player to read outside the game, like why a recording stopped.
+    /// Backends that take over the terminal have to hold on to it until they give it back.
+    fn message(&mut self, text: &str) {
+        eprintln!("{text}");
+    }
+
+    /// Whether ticks should follow the wall clock. Backends nobody watches live (headless,
+    /// tests) say no and get exactly one tick per `poll`, as fast as they can take them.
+    fn is_realtime(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,220 @@
+//! A desktop window, courtesy of minifb.
+
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use ::minifb::{InputCallback, KeyRepeat, MouseMode, Scale, ScaleMode, Window};
+
+use super::{Backend, WindowOptions};
+use crate::game::{Event, Frame, Key, MouseButton, key::for_each_key};
+
+pub struct Minifb {
+    win: Window,
+    name: String,
+    /// Of the game, the window is usually bigger.
+    size: [usize; 2],
+    options: WindowOptions,
+    /// minifb only tells us how the mouse is now, events come from comparing with last time.
+    mouse: Option<[i32; 2]>,
+    buttons: [bool; 3],
+    /// Scrolling that hasn't added up to a whole notch yet.
+    scroll: [f32; 2],
+    /// Filled in by minifb as characters are typed.
+    typed: Rc<RefCell<Vec<char>>>,
+}
+
+impl Minifb {
+    pub fn new(name: &str, width: usize, height: usize, options: &WindowOptions) -> Self {
+        let typed = Rc::new(RefCell::new(Vec::new()));
+        Self {
+            win: open(name, [width, height], options, &typed),
+            name: name.to_owned(),
+            size: [width, height],
+            options: *options,
+            mouse: None,
+            buttons: [false; 3],
+            scroll: [0.0; 2],
+            typed,
+        }
+    }
+}
+
+impl Backend for Minifb {
+    fn is_open(&self) -> bool {
+        self.win.is_open() && !self.win.is_key_down(::minifb::Key::Escape)
+    }
+
+    fn poll(&mut self) -> Vec<Event> {
+        let mut events: Vec<_> = self
+            .win
+            .get_keys_pressed(KeyRepeat::No)
+            .into_iter()
+            .filter_map(convert)
+            .map(Event::KeyPressed)
+            .collect();
+        events.extend(
+            self.win
+                .get_keys_released()
+                .into_iter()
+                .filter_map(convert)
+                .map(Event::KeyReleased),
+        );
+
+        events.extend(self.typed.borrow_mut().drain(..).map(Event::Char));
+
+        // moves go first, so a click lands where the mouse is now
+        if let Some((x, y)) = self.win.get_unscaled_mouse_pos(MouseMode::Pass) {
+            let (width, height) = self.win.get_size();
+            let mouse = to_game([x, y], [width, height], self.size);
+            if self.mouse.replace(mouse) != Some(mouse) {
+                events.push(Event::MouseMoved {
+                    x: mouse[0],
+                    y: mouse[1],
+                });
+            }
+        }
+
+        for &button in MouseButton::ALL {
+            let down = self.win.get_mouse_down(convert_button(button));
+            if std::mem::replace(&mut self.buttons[button as usize], down) != down {
+                events.push(match down {
+                    true => Event::MousePressed(button),
+                    false => Event::MouseReleased(button),
+                });
+            }
+        }
+
+        if let Some((x, y)) = self.win.get_scroll_wheel() {
+            // minifb counts up and left as positive
+            self.scroll[0] -= x;
+            self.scroll[1] -= y;
+        }
+        let notches = self.scroll.map(f32::trunc);
+        if notches != [0.0; 2] {
+            self.scroll = [self.scroll[0] - notches[0], self.scroll[1] - notches[1]];
+            events.push(Event::Scrolled {
+                x: notches[0] as i32,
+                y: notches[1] as i32,
+            });
+        }
+
+        events
+    }
+
+    /// minifb 0.23 can't go truly fullscreen, the closest it gets is a borderless window in
+    /// the corner as big as the screen allows at a power of two scale.
+    fn toggle_fullscreen(&mut self) {
+        self.options.fullscreen = !self.options.fullscreen;
+        self.win = open(&self.name, self.size, &self.options, &self.typed);
+    }
+
+    fn present(&mut self, frame: &Frame) {
+        self.win
+            .update_with_buffer(&frame.pixels, frame.width, frame.height)
+            .unwrap();
+    }
+}
+
+fn open(
+    name: &str,
+    [width, height]: [usize; 2],
+    options: &WindowOptions,
+    typed: &Rc<RefCell<Vec<char>>>,
+) -> Window {
+    let scale = options.scale.max(1) as usize;
+    let mut win = if options.fullscreen {
+        let mut win = Window::new(
+            name,
+            width,
+            height,
+            ::minifb::WindowOptions {
+                borderless: true,
+                scale: Scale::FitScreen,
+                scale_mode: ScaleMode::AspectRatioStretch,
+                topmost: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        win.set_position(0, 0);
+        win
+    } else {
+        // minifb's own scaling only does powers of two, stretching the frame does any size
+        Window::new(
+            name,
+            width * scale,
+            height * scale,
+            ::minifb::WindowOptions {
+                resize: options.resizable,
+                scale_mode: ScaleMode::AspectRatioStretch,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    };
+
+    win.limit_update_rate(Some(Duration::from_micros(16600)));
+    win.set_input_callback(Box::new(CharSink(typed.clone())));
+    win
+}
+
+/// The game pixel under `mouse`, a position in the window. minifb stretches the frame to fit
+/// the window, keeping its aspect ratio, and centers it.
+fn to_game(mouse: [f32; 2], window: [usize; 2], game: [usize; 2]) -> [i32; 2] {
+    let window = window.map(|len| len as f32);
+    let game = game.map(|len| len as f32);
+    let fit = (window[0] / game[0]).min(window[1] / game[1]);
+    [0, 1].map(|axis| {
+        let offset = (window[axis] - game[axis] * fit) / 2.0;
+        ((mouse[axis] - offset) / fit).floor() as i32
+    })
+}
+
+struct CharSink(Rc<RefCell<Vec<char>>>);
+
+impl InputCallback for CharSink {
+    fn add_char(&mut self, c: u32) {
+        // some platforms pass control characters along anyway, games get those as keys
+        if let Some(c) = char::from_u32(c).filter(|c| !c.is_control()) {
+            self.0.borrow_mut().push(c);
+        }
+    }
+}
+
+fn convert_button(button: MouseButton) -> ::minifb::MouseButton {
+    match button {
+        MouseButton::Left => ::minifb::MouseButton::Left,
+        MouseButton::Middle => ::minifb::MouseButton::Middle,
+        MouseButton::Right => ::minifb::MouseButton::Right,
+    }
+}
+
+macro_rules! convert_keys {
+    ($($key:ident),* $(,)?) => {
+        fn convert(key: ::minifb::Key) -> Option<Key> {
+            match key {
+                $(::minifb::Key::$key => Some(Key::$key),)*
+                _ => None,
+            }
+        }
+    };
+}
+for_each_key!(convert_keys);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mouse_in_letterboxed_window() {
+        let game = [100, 50];
+        // exactly twice as big
+        assert_eq!(to_game([0.0, 0.0], [200, 100], game), [0, 0]);
+        assert_eq!(to_game([199.5, 99.5], [200, 100], game), [99, 49]);
+        // bars above and below
+        assert_eq!(to_game([100.0, 0.0], [200, 200], game), [50, -25]);
+        assert_eq!(to_game([100.0, 100.0], [200, 200], game), [50, 25]);
+        // bars left and right
+        assert_eq!(to_game([400.0, 150.0], [800, 300], game), [50, 25]);
+        assert_eq!(to_game([40.0, 0.0], [800, 300], game), [-10, 0]);
+    }
+}
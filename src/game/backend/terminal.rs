@@ -0,0 +1,437 @@
+//! Playing in a terminal, for SSH sessions and machines without a display.
+//!
+//! Every character cell shows two game pixels, the top one as the foreground color of `▀` and
+//! the bottom one as the background, in 24-bit color. Games bigger than the terminal are
+//! shrunk by a whole factor, which loses detail but keeps them playable. Only cells that
+//! changed are redrawn, so a mostly still game is cheap over a slow link.
+//!
+//! The terminal goes into raw mode with `stty`, so this needs a Unix-like system. Escape or
+//! Ctrl-C quits.
+//!
+//! Terminals only report key presses, never releases. A key counts as down until it stops
+//! repeating, and the terminal's own repeats aren't passed on as presses (see
+//! `Game::KEY_REPEAT` for repeats). Typed characters always are, and so are Backspace and
+//! Enter.
+
+use std::{
+    io::{self, Read, Write},
+    process::{Command, Stdio},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::Backend;
+use crate::game::{Color, Event, Frame, Key};
+
+/// Longer than terminals wait before they start repeating a held key.
+const RELEASE_AFTER: Duration = Duration::from_millis(600);
+
+pub struct Terminal {
+    /// Chunks of stdin, read on another thread so polling never blocks.
+    input: Receiver<Vec<u8>>,
+    open: bool,
+    /// `stty` settings to go back to.
+    saved: String,
+    /// In character cells.
+    size: [usize; 2],
+    /// What each cell shows, as (top, bottom) pixels, `None` for unknown.
+    cells: Vec<Option<(u32, u32)>>,
+    /// Keys we consider down, and when the terminal last sent them.
+    down: Vec<(Key, Instant)>,
+    /// From `message`, for stderr once the game is off the screen.
+    messages: Vec<String>,
+}
+
+impl Terminal {
+    pub fn new() -> io::Result<Self> {
+        let saved = stty(&["-g"])?.trim().to_owned();
+        let size = stty(&["size"])
+            .ok()
+            .and_then(|size| {
+                let (rows, cols) = size.trim().split_once(' ')?;
+                Some([cols.parse().ok()?, rows.parse().ok()?])
+            })
+            .unwrap_or([80, 24]);
+        stty(&["raw", "-echo"])?;
+
+        // alternate screen, hidden cursor, cleared
+        print!("\x1b[?1049h\x1b[?25l\x1b[2J");
+        io::stdout().flush()?;
+
+        let (send, input) = mpsc::channel();
+        thread::spawn(move || {
+            let mut buf = [0; 64];
+            let mut stdin = io::stdin();
+            while let Ok(len @ 1..) = stdin.read(&mut buf) {
+                if send.send(buf[..len].to_vec()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            input,
+            open: true,
+            saved,
+            size,
+            cells: Vec::new(),
+            down: Vec::new(),
+            messages: Vec::new(),
+        })
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        print!("\x1b[0m\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+        let _ = stty(&[self.saved.as_str()]);
+        for text in &self.messages {
+            eprintln!("{text}");
+        }
+    }
+}
+
+impl Backend for Terminal {
+    fn is_open(&self) -> bool {
+        self.open
+    }
+
+    fn poll(&mut self) -> Vec<Event> {
+        let mut bytes = Vec::new();
+        loop {
+            match self.input.try_recv() {
+                Ok(chunk) => bytes.extend(chunk),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.open = false;
+                    break;
+                }
+            }
+        }
+
+        let now = Instant::now();
+        let mut events = Vec::new();
+        for stroke in parse(&bytes) {
+            if let Some(key) = stroke.key {
+                events.extend(press(&mut self.down, key, now));
+            }
+            events.extend(stroke.c.map(Event::Char));
+            if stroke.quit {
+                self.open = false;
+            }
+        }
+        self.down.retain(|&(key, at)| {
+            let down = now - at < RELEASE_AFTER;
+            if !down {
+                events.push(Event::KeyReleased(key));
+            }
+            down
+        });
+        events
+    }
+
+    /// Writing to stderr now would scribble over the game, and the cells it hit wouldn't be
+    /// drawn again until they change.
+    fn message(&mut self, text: &str) {
+        self.messages.push(text.to_owned());
+    }
+
+    fn present(&mut self, frame: &Frame) {
+        let out = draw(frame, self.size, &mut self.cells);
+        let mut stdout = io::stdout().lock();
+        // nothing sensible to do if the terminal has gone away, `poll` will notice
+        let _ = stdout
+            .write_all(out.as_bytes())
+            .and_then(|()| stdout.flush());
+    }
+}
+
+fn stty(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!("stty {} failed", args.join(" "))));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The escape codes to update the terminal from what `cells` says it shows to `frame`, shrunk
+/// to fit in `size` cells.
+fn draw(frame: &Frame, [cols, rows]: [usize; 2], cells: &mut Vec<Option<(u32, u32)>>) -> String {
+    let step = (frame.width.div_ceil(cols.max(1)))
+        .max(frame.height.div_ceil(rows.max(1) * 2))
+        .max(1);
+    let width = frame.width.div_ceil(step);
+    let height = frame.height.div_ceil(step * 2);
+    if cells.len() != width * height {
+        *cells = vec![None; width * height];
+    }
+
+    // the bottom row of cells can hang off the frame
+    let pixel = |x: usize, y: usize| match [x * step, y * step] {
+        [x, y] if x < frame.width && y < frame.height => frame.get(x, y),
+        _ => 0,
+    };
+    let mut out = String::new();
+    // where the cursor is after the last cell we drew
+    let mut cursor = None;
+    for row in 0..height {
+        for col in 0..width {
+            let cell = (pixel(col, row * 2), pixel(col, row * 2 + 1));
+            let known = &mut cells[row * width + col];
+            if *known == Some(cell) {
+                continue;
+            }
+            *known = Some(cell);
+
+            if cursor != Some((row, col)) {
+                out += &format!("\x1b[{};{}H", row + 1, col + 1);
+            }
+            let [top, bottom] = [cell.0, cell.1].map(Color::from_pixel);
+            out += &format!(
+                "\x1b[38;2;{};{};{};48;2;{};{};{}m▀",
+                top.r, top.g, top.b, bottom.r, bottom.g, bottom.b
+            );
+            cursor = Some((row, col + 1));
+        }
+    }
+    out
+}
+
+/// One key press worth of input.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+struct Stroke {
+    key: Option<Key>,
+    c: Option<char>,
+    quit: bool,
+}
+
+/// Mark `key` down as of `now`, returns the press to pass on. Backspace and Enter are edits
+/// for `Input::get_typed`, so every one of them is passed on, like typed characters.
+fn press(down: &mut Vec<(Key, Instant)>, key: Key, now: Instant) -> Option<Event> {
+    let edit = matches!(key, Key::Backspace | Key::Enter);
+    match down.iter_mut().find(|(down, _)| *down == key) {
+        Some((_, at)) => {
+            *at = now;
+            edit.then_some(Event::KeyPressed(key))
+        }
+        None => {
+            down.push((key, now));
+            Some(Event::KeyPressed(key))
+        }
+    }
+}
+
+fn parse(bytes: &[u8]) -> Vec<Stroke> {
+    let text = String::from_utf8_lossy(bytes);
+    let mut strokes = Vec::new();
+    let mut rest = text.as_ref();
+    while let Some(c) = rest.chars().next() {
+        if let Some((key, len)) = escape(rest) {
+            strokes.push(Stroke {
+                key,
+                quit: key == Some(Key::Escape),
+                ..Default::default()
+            });
+            rest = &rest[len..];
+            continue;
+        }
+        rest = &rest[c.len_utf8()..];
+        strokes.push(match c {
+            '\x03' => Stroke {
+                quit: true,
+                ..Default::default()
+            },
+            '\r' | '\n' => key(Key::Enter),
+            '\x7f' | '\x08' => key(Key::Backspace),
+            '\t' => key(Key::Tab),
+            c if c.is_control() => Stroke::default(),
+            c => Stroke {
+                key: printable_key(c),
+                c: Some(c),
+                quit: false,
+            },
+        });
+    }
+    strokes
+}
+
+fn key(key: Key) -> Stroke {
+    Stroke {
+        key: Some(key),
+        ..Default::default()
+    }
+}
+
+/// A key from an escape sequence at the start of `text`, and how many bytes it took. Unknown
+/// sequences are swallowed whole, with no key.
+fn escape(text: &str) -> Option<(Option<Key>, usize)> {
+    let rest = text.strip_prefix('\x1b')?;
+    let Some(body) = rest.strip_prefix('[').or_else(|| rest.strip_prefix('O')) else {
+        // nothing after it, or not a sequence we know, so it's the key itself
+        return Some((Some(Key::Escape), 1));
+    };
+    // parameters, then a final byte
+    let params = body
+        .bytes()
+        .take_while(|b| (0x20..0x40).contains(b))
+        .count();
+    let Some(last) = body[params..].chars().next() else {
+        return Some((None, text.len()));
+    };
+    let key = match (&body[..params], last) {
+        ("", 'A') => Some(Key::Up),
+        ("", 'B') => Some(Key::Down),
+        ("", 'C') => Some(Key::Right),
+        ("", 'D') => Some(Key::Left),
+        ("", 'H') | ("1", '~') => Some(Key::Home),
+        ("", 'F') | ("4", '~') => Some(Key::End),
+        ("", 'P') => Some(Key::F1),
+        ("", 'Q') => Some(Key::F2),
+        ("", 'R') => Some(Key::F3),
+        ("", 'S') => Some(Key::F4),
+        (number, '~') => match number {
+            "2" => Some(Key::Insert),
+            "3" => Some(Key::Delete),
+            "5" => Some(Key::PageUp),
+            "6" => Some(Key::PageDown),
+            "15" => Some(Key::F5),
+            "17" => Some(Key::F6),
+            "18" => Some(Key::F7),
+            "19" => Some(Key::F8),
+            "20" => Some(Key::F9),
+            "21" => Some(Key::F10),
+            "23" => Some(Key::F11),
+            "24" => Some(Key::F12),
+            _ => None,
+        },
+        _ => None,
+    };
+    Some((key, 2 + params + last.len_utf8()))
+}
+
+/// The key that types `c` without shift, if there is one.
+fn printable_key(c: char) -> Option<Key> {
+    Some(match c.to_ascii_lowercase() {
+        c @ 'a'..='z' => Key::ALL[Key::A as usize + (c as u8 - b'a') as usize],
+        c @ '0'..='9' => Key::ALL[Key::Key0 as usize + (c as u8 - b'0') as usize],
+        ' ' => Key::Space,
+        '\'' => Key::Apostrophe,
+        '`' => Key::Backquote,
+        '\\' => Key::Backslash,
+        ',' => Key::Comma,
+        '=' => Key::Equal,
+        '[' => Key::LeftBracket,
+        '-' => Key::Minus,
+        '.' => Key::Period,
+        ']' => Key::RightBracket,
+        ';' => Key::Semicolon,
+        '/' => Key::Slash,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys() {
+        let strokes = parse("aZ!\x1b[A\x1bOQ\x1b[24~\x1b[99~\r\x7f".as_bytes());
+        let keys: Vec<_> = strokes.iter().map(|stroke| stroke.key).collect();
+        assert_eq!(
+            keys,
+            [
+                Some(Key::A),
+                Some(Key::Z),
+                None,
+                Some(Key::Up),
+                Some(Key::F2),
+                Some(Key::F12),
+                None,
+                Some(Key::Enter),
+                Some(Key::Backspace),
+            ]
+        );
+        let chars: String = strokes.iter().filter_map(|stroke| stroke.c).collect();
+        assert_eq!(chars, "aZ!");
+        assert!(!strokes.iter().any(|stroke| stroke.quit));
+
+        assert!(parse(b"\x1b")[0].quit);
+        assert!(parse(b"x\x03")[1].quit);
+        assert_eq!(parse("é".as_bytes())[0].c, Some('é'));
+    }
+
+    #[test]
+    fn edits_are_never_swallowed() {
+        let mut down = Vec::new();
+        let now = Instant::now();
+        assert_eq!(
+            press(&mut down, Key::A, now),
+            Some(Event::KeyPressed(Key::A))
+        );
+        assert_eq!(press(&mut down, Key::A, now), None);
+        for _ in 0..2 {
+            assert_eq!(
+                press(&mut down, Key::Backspace, now),
+                Some(Event::KeyPressed(Key::Backspace))
+            );
+        }
+        assert_eq!(down.len(), 2);
+    }
+
+    #[test]
+    fn digits_line_up() {
+        assert_eq!(printable_key('0'), Some(Key::Key0));
+        assert_eq!(printable_key('9'), Some(Key::Key9));
+        assert_eq!(printable_key('m'), Some(Key::M));
+    }
+
+    #[test]
+    fn drawing() {
+        let mut frame = Frame::new(4, 3);
+        frame.pixels[0] = Color::rgb(1, 2, 3).to_pixel();
+        frame.pixels[4] = Color::rgb(4, 5, 6).to_pixel();
+        let mut cells = Vec::new();
+
+        let out = draw(&frame, [80, 24], &mut cells);
+        assert_eq!(cells.len(), 4 * 2);
+        assert!(out.starts_with("\x1b[1;1H\x1b[38;2;1;2;3;48;2;4;5;6m▀\x1b[38;2;0;0;0;"));
+        assert_eq!(out.matches('▀').count(), 8);
+        // one cursor move per row, the cells of a row follow each other
+        assert_eq!(out.matches('H').count(), 2);
+
+        assert_eq!(draw(&frame, [80, 24], &mut cells), "");
+        frame.pixels[11] = Color::WHITE.to_pixel();
+        assert_eq!(
+            draw(&frame, [80, 24], &mut cells),
+            "\x1b[2;4H\x1b[38;2;255;255;255;48;2;0;0;0m▀"
+        );
+
+        // shrunk to fit
+        let out = draw(&Frame::new(256, 320), [80, 24], &mut cells);
+        assert_eq!(cells.len(), 37 * 23);
+        assert_eq!(out.matches('▀').count(), 37 * 23);
+    }
+
+    #[test]
+    fn shrinking_odd_sizes() {
+        // every third pixel, the last row of cells only half on the frame
+        let white = Color::WHITE.to_pixel();
+        let mut frame = Frame::new(256, 320);
+        frame.pixels[318 * 256] = white;
+        let mut cells = Vec::new();
+        draw(&frame, [120, 60], &mut cells);
+        assert_eq!(cells.len(), 86 * 54);
+        assert_eq!(cells[53 * 86], Some((white, 0)));
+
+        let out = draw(&Frame::new(100, 5), [80, 24], &mut cells);
+        assert_eq!(cells.len(), 50 * 2);
+        assert_eq!(out.matches('▀').count(), 50 * 2);
+    }
+}
@@ -0,0 +1,123 @@
+//! Colors and how they combine with what's already been drawn.
+
+/// 8-bit RGBA, straight (not premultiplied) alpha.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const BLACK: Self = Self::rgb(0, 0, 0);
+    pub const WHITE: Self = Self::rgb(255, 255, 255);
+    pub const TRANSPARENT: Self = Self::rgba(0, 0, 0, 0);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self::rgba(r, g, b, 255)
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    pub const fn with_alpha(self, a: u8) -> Self {
+        Self { a, ..self }
+    }
+
+    /// `0xAARRGGBB`, the layout of `Frame::pixels`.
+    pub const fn to_pixel(self) -> u32 {
+        u32::from_le_bytes([self.b, self.g, self.r, self.a])
+    }
+
+    pub const fn from_pixel(pixel: u32) -> Self {
+        let [b, g, r, a] = pixel.to_le_bytes();
+        Self { r, g, b, a }
+    }
+}
+
+/// How a color drawn on top combines with the one below. In every mode the top color's alpha
+/// scales its effect, so alpha 0 leaves the frame untouched.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Blend {
+    /// Ordinary "over" compositing.
+    #[default]
+    Normal,
+    /// Brightens, good for glows and flashes.
+    Add,
+    /// Darkens, good for shadows and tinting.
+    Multiply,
+}
+
+impl Blend {
+    /// Draw `src` over `dst`. The frame is opaque, so the result always is too.
+    pub fn apply(self, dst: Color, src: Color) -> Color {
+        let a = u16::from(src.a);
+        let channel = |d: u8, s: u8| -> u8 {
+            let (d, s) = (u16::from(d), u16::from(s));
+            let out = match self {
+                Self::Normal => div255(s * a + d * (255 - a)),
+                Self::Add => d + div255(s * a),
+                // lerp between leaving dst alone and multiplying it by src
+                Self::Multiply => div255(d * div255(s * a + 255 * (255 - a))),
+            };
+            out.min(255) as u8
+        };
+        Color::rgb(
+            channel(dst.r, src.r),
+            channel(dst.g, src.g),
+            channel(dst.b, src.b),
+        )
+    }
+}
+
+/// `x / 255` rounded, exact for `x <= 255 * 255`.
+fn div255(x: u16) -> u16 {
+    let x = u32::from(x) + 128;
+    ((x + (x >> 8)) >> 8) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixels() {
+        let color = Color::rgba(1, 2, 3, 4);
+        assert_eq!(color.to_pixel(), 0x0401_0203);
+        assert_eq!(Color::from_pixel(color.to_pixel()), color);
+    }
+
+    #[test]
+    fn rounding() {
+        for x in 0..=255 * 255 {
+            assert_eq!(div255(x), (x as f64 / 255.0).round() as u16, "{x}");
+        }
+    }
+
+    #[test]
+    fn blending() {
+        let dst = Color::rgb(100, 200, 50);
+        let src = Color::rgb(200, 100, 255);
+        for blend in [Blend::Normal, Blend::Add, Blend::Multiply] {
+            assert_eq!(blend.apply(dst, src.with_alpha(0)), dst, "{blend:?}");
+        }
+
+        assert_eq!(Blend::Normal.apply(dst, src), src);
+        assert_eq!(
+            Blend::Normal.apply(dst, src.with_alpha(128)),
+            Color::rgb(150, 150, 153)
+        );
+        assert_eq!(Blend::Add.apply(dst, src), Color::rgb(255, 255, 255));
+        assert_eq!(
+            Blend::Add.apply(dst, src.with_alpha(51)),
+            Color::rgb(140, 220, 101)
+        );
+        assert_eq!(Blend::Multiply.apply(dst, src), Color::rgb(78, 78, 50));
+        assert_eq!(
+            Blend::Multiply.apply(dst, Color::BLACK.with_alpha(128)),
+            Color::rgb(50, 100, 25)
+        );
+    }
+}
@@ -0,0 +1,173 @@
+//! Compare rendered frames against golden images checked into the repo.
+//!
+//! Golden images are PPMs. Run the tests with `UPDATE_GOLDEN=1` to write them instead of
+//! comparing against them, then look at the result before committing it.
+
+use std::{fmt, path::Path};
+
+use super::Frame;
+
+/// Panics with a readable report if `frame` doesn't match the golden image at `path`.
+///
+/// On a mismatch the frame is saved next to the golden image as `<name>.actual.ppm`.
+#[track_caller]
+pub fn assert_golden(frame: &Frame, path: impl AsRef<Path>) {
+    let path = path.as_ref();
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        frame
+            .save(path)
+            .unwrap_or_else(|err| panic!("failed to write {}: {err}", path.display()));
+        return;
+    }
+
+    let golden = std::fs::read(path)
+        .and_then(|bytes| Frame::read_ppm(&bytes))
+        .unwrap_or_else(|err| {
+            panic!(
+                "failed to read golden image {}: {err}\n\
+                 rerun with UPDATE_GOLDEN=1 to create it",
+                path.display()
+            )
+        });
+
+    if let Some(diff) = Diff::new(&golden, frame) {
+        let actual = path.with_extension("actual.ppm");
+        let saved = match frame.save(&actual) {
+            Ok(()) => format!("actual frame saved to {}", actual.display()),
+            Err(err) => format!("failed to save actual frame: {err}"),
+        };
+        panic!(
+            "frame doesn't match {}\n{diff}{saved}\n\
+             rerun with UPDATE_GOLDEN=1 if the change is intended",
+            path.display()
+        );
+    }
+}
+
+/// How two frames differ.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Diff {
+    Size {
+        expected: [usize; 2],
+        actual: [usize; 2],
+    },
+    Pixels {
+        count: usize,
+        /// Smallest rectangle containing every differing pixel, `[x0, y0, x1, y1]` inclusive.
+        bounds: [usize; 4],
+        /// The first few differences as `(x, y, expected, actual)`.
+        samples: Vec<(usize, usize, u32, u32)>,
+    },
+}
+
+impl Diff {
+    const SAMPLES: usize = 8;
+
+    pub fn new(expected: &Frame, actual: &Frame) -> Option<Self> {
+        if (expected.width, expected.height) != (actual.width, actual.height) {
+            return Some(Self::Size {
+                expected: [expected.width, expected.height],
+                actual: [actual.width, actual.height],
+            });
+        }
+
+        let mut count = 0;
+        let mut bounds = [usize::MAX, usize::MAX, 0, 0];
+        let mut samples = Vec::new();
+        for (i, (&e, &a)) in expected.pixels.iter().zip(&actual.pixels).enumerate() {
+            // alpha isn't shown, and doesn't survive a trip through PPM
+            let (e, a) = (e & 0xff_ffff, a & 0xff_ffff);
+            if e == a {
+                continue;
+            }
+            let (x, y) = (i % expected.width, i / expected.width);
+            count += 1;
+            bounds = [
+                bounds[0].min(x),
+                bounds[1].min(y),
+                bounds[2].max(x),
+                bounds[3].max(y),
+            ];
+            if samples.len() < Self::SAMPLES {
+                samples.push((x, y, e, a));
+            }
+        }
+
+        (count > 0).then_some(Self::Pixels {
+            count,
+            bounds,
+            samples,
+        })
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Size { expected, actual } => writeln!(
+                f,
+                "expected a {}x{} frame, got {}x{}",
+                expected[0], expected[1], actual[0], actual[1]
+            ),
+            Self::Pixels {
+                count,
+                bounds: [x0, y0, x1, y1],
+                samples,
+            } => {
+                writeln!(
+                    f,
+                    "{count} pixels differ, all within ({x0}, {y0})..=({x1}, {y1})"
+                )?;
+                for (x, y, expected, actual) in samples {
+                    writeln!(
+                        f,
+                        "  ({x}, {y}): expected #{expected:06x}, got #{actual:06x}"
+                    )?;
+                }
+                if count > &samples.len() {
+                    writeln!(f, "  ...")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical() {
+        let frame = Frame::new(3, 3);
+        assert_eq!(Diff::new(&frame, &frame), None);
+    }
+
+    #[test]
+    fn alpha_is_ignored() {
+        let expected = Frame::new(1, 1);
+        let mut actual = expected.clone();
+        actual.pixels[0] = 0xff00_0000;
+        assert_eq!(Diff::new(&expected, &actual), None);
+    }
+
+    #[test]
+    fn report() {
+        let expected = Frame::new(4, 4);
+        let mut actual = expected.clone();
+        actual.pixels[4 + 2] = 0xffff_0000;
+        actual.pixels[3 * 4 + 1] = 0xff00_00ff;
+
+        let diff = Diff::new(&expected, &actual).unwrap();
+        assert_eq!(
+            diff.to_string(),
+            "2 pixels differ, all within (1, 1)..=(2, 3)\n\
+             \x20 (2, 1): expected #000000, got #ff0000\n\
+             \x20 (1, 3): expected #000000, got #0000ff\n"
+        );
+
+        let diff = Diff::new(&expected, &Frame::new(4, 5)).unwrap();
+        assert_eq!(diff.to_string(), "expected a 4x4 frame, got 4x5\n");
+    }
+}
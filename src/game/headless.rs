@@ -0,0 +1,114 @@
+//! Run a `Game` without a window.
+//!
+//! Input comes from a script, one list of events per tick, and every tick is rasterized into
+//! a `Frame` in memory. Useful for tests and for machines that have no display.
+
+use std::collections::VecDeque;
+
+use super::{Event, Frame, Game, Key, backend::Backend};
+
+/// A `Backend` that plays back a script and keeps every frame it is shown.
+pub struct Headless {
+    script: VecDeque<Vec<Event>>,
+    frames: Vec<Frame>,
+}
+
+impl Headless {
+    pub fn new(script: impl IntoIterator<Item = Vec<Event>>) -> Self {
+        Self {
+            script: script.into_iter().collect(),
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn into_frames(self) -> Vec<Frame> {
+        self.frames
+    }
+}
+
+impl Backend for Headless {
+    fn is_open(&self) -> bool {
+        !self.script.is_empty()
+    }
+
+    fn poll(&mut self) -> Vec<Event> {
+        self.script.pop_front().unwrap_or_default()
+    }
+
+    fn present(&mut self, frame: &Frame) {
+        self.frames.push(frame.clone());
+    }
+
+    fn is_realtime(&self) -> bool {
+        false
+    }
+}
+
+/// What a headless run leaves behind.
+pub struct Run {
+    /// State after the last scripted tick.
+    pub state: u64,
+    /// One frame per tick, in order.
+    pub frames: Vec<Frame>,
+}
+
+/// Run `G` from `G::init()`, one tick per entry in `script`.
+pub fn run<G: Game>(script: impl IntoIterator<Item = Vec<Event>>) -> Run {
+    run_from::<G>(G::init(), script)
+}
+
+/// The events of typing `text` in one tick. `'\n'` presses Enter and `'\u{8}'` Backspace.
+pub fn typing(text: &str) -> Vec<Event> {
+    text.chars()
+        .map(|c| match c {
+            '\n' => Event::KeyPressed(Key::Enter),
+            '\u{8}' => Event::KeyPressed(Key::Backspace),
+            c => Event::Char(c),
+        })
+        .collect()
+}
+
+/// Like `run` but starting from an arbitrary state.
+pub fn run_from<G: Game>(state: u64, script: impl IntoIterator<Item = Vec<Event>>) -> Run {
+    let mut backend = Headless::new(script);
+    let state = G::run_on(&mut backend, state);
+    Run {
+        state,
+        frames: backend.into_frames(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{
+        Color,
+        test_games::{Counter, Namer},
+    };
+
+    #[test]
+    fn scripted_input_reaches_the_game() {
+        let press = |key| vec![Event::KeyPressed(key)];
+        let run = run::<Counter>([press(Key::A), vec![], press(Key::B), press(Key::C)]);
+        assert_eq!(run.state, 3);
+        assert_eq!(run.frames.len(), 4);
+    }
+
+    #[test]
+    fn frames_are_rasterized() {
+        let run = run_from::<Counter>(4, [vec![]]);
+        let frame = &run.frames[0];
+        assert_eq!(
+            (frame.width, frame.height),
+            (Counter::WIDTH, Counter::HEIGHT)
+        );
+        assert_eq!(frame.get(4, 1), Color::rgb(1, 2, 3).to_pixel());
+        assert_eq!(frame.pixels.iter().filter(|&&p| p != 0).count(), 1);
+    }
+
+    #[test]
+    fn typing_reaches_the_game() {
+        let run = run::<Namer>([typing("X\n"), typing("AB"), typing("\u{8}Cé")]);
+        assert_eq!(run.state.to_be_bytes(), *b"\0\0\0\0\0\0AC");
+    }
+}
@@ -0,0 +1,151 @@
+//! What `Input` remembers between ticks.
+
+use super::{Event, Key, MouseButton};
+
+/// Which keys and buttons are down and where the mouse is. Follows from the events alone, so
+/// replays and rewinds get it right without saving it.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Held {
+    /// One bit per `Key`.
+    pub keys: u128,
+    /// In game pixels. Can be off screen, or unknown before the mouse first moves.
+    pub mouse: Option<[i32; 2]>,
+    /// One bit per `MouseButton`.
+    pub buttons: u8,
+    /// One bit per `Game::ACTIONS`.
+    pub actions: u64,
+}
+
+/// How many actions `Held` has bits for.
+pub const MAX_ACTIONS: usize = u64::BITS as usize;
+
+/// The bit of `action` in `Held::actions`, none if it has no bit.
+fn action_bit(action: usize) -> u64 {
+    match action < MAX_ACTIONS {
+        true => 1 << action,
+        false => 0,
+    }
+}
+
+impl Held {
+    pub fn apply(&mut self, events: &[Event]) {
+        for event in events {
+            match *event {
+                Event::KeyPressed(key) => self.keys |= 1 << key as u8,
+                Event::KeyReleased(key) => self.keys &= !(1 << key as u8),
+                Event::MouseMoved { x, y } => self.mouse = Some([x, y]),
+                Event::MousePressed(button) => self.buttons |= 1 << button as u8,
+                Event::MouseReleased(button) => self.buttons &= !(1 << button as u8),
+                Event::ActionPressed(action) => self.actions |= action_bit(action.into()),
+                Event::ActionReleased(action) => self.actions &= !action_bit(action.into()),
+                Event::Scrolled { .. } | Event::Char(_) => {}
+            }
+        }
+    }
+
+    /// The fewest events that take what `self` holds to what `to` does. Actions are left out,
+    /// they follow from the keys (see `Bindings::translate`).
+    pub fn changes(&self, to: &Held) -> Vec<Event> {
+        let mut events = Vec::new();
+        if let Some([x, y]) = to.mouse
+            && to.mouse != self.mouse
+        {
+            events.push(Event::MouseMoved { x, y });
+        }
+        for &key in Key::ALL {
+            match (self.is_key_down(key), to.is_key_down(key)) {
+                (false, true) => events.push(Event::KeyPressed(key)),
+                (true, false) => events.push(Event::KeyReleased(key)),
+                _ => {}
+            }
+        }
+        for &button in MouseButton::ALL {
+            match (self.is_mouse_down(button), to.is_mouse_down(button)) {
+                (false, true) => events.push(Event::MousePressed(button)),
+                (true, false) => events.push(Event::MouseReleased(button)),
+                _ => {}
+            }
+        }
+        events
+    }
+
+    pub fn is_key_down(&self, key: Key) -> bool {
+        self.keys & 1 << key as u8 != 0
+    }
+
+    pub fn is_mouse_down(&self, button: MouseButton) -> bool {
+        self.buttons & 1 << button as u8 != 0
+    }
+
+    /// Never for actions past `MAX_ACTIONS`.
+    pub fn is_action_down(&self, action: usize) -> bool {
+        self.actions & action_bit(action) != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_key_has_a_bit() {
+        assert!(Key::ALL.len() <= u128::BITS as usize);
+    }
+
+    #[test]
+    fn held() {
+        let mut held = Held::default();
+        held.apply(&[
+            Event::MouseMoved { x: -3, y: 4 },
+            Event::MousePressed(MouseButton::Left),
+            Event::MousePressed(MouseButton::Right),
+            Event::Scrolled { x: 0, y: 1 },
+            Event::MouseReleased(MouseButton::Left),
+            Event::KeyPressed(Key::NumPadEnter),
+            Event::KeyPressed(Key::A),
+            Event::KeyReleased(Key::A),
+        ]);
+        assert_eq!(held.mouse, Some([-3, 4]));
+        assert!(!held.is_mouse_down(MouseButton::Left));
+        assert!(held.is_mouse_down(MouseButton::Right));
+        assert!(held.is_key_down(Key::NumPadEnter));
+        assert!(!held.is_key_down(Key::A));
+    }
+
+    #[test]
+    fn actions_past_the_last_bit() {
+        let mut held = Held::default();
+        held.apply(&[Event::ActionPressed(63), Event::ActionPressed(64)]);
+        held.apply(&[Event::ActionPressed(200)]);
+        assert_eq!(held.actions, 1 << 63);
+        assert!(held.is_action_down(63));
+        assert!(!held.is_action_down(64));
+        assert!(!held.is_action_down(usize::MAX));
+    }
+
+    #[test]
+    fn changes() {
+        let mut before = Held::default();
+        before.apply(&[Event::KeyPressed(Key::A), Event::KeyPressed(Key::B)]);
+        let mut after = before;
+        after.apply(&[
+            Event::KeyReleased(Key::A),
+            Event::KeyPressed(Key::C),
+            Event::KeyReleased(Key::C),
+            Event::KeyPressed(Key::D),
+            Event::MousePressed(MouseButton::Left),
+            Event::MouseMoved { x: 1, y: 2 },
+            Event::Char('d'),
+        ]);
+        assert_eq!(
+            before.changes(&after),
+            [
+                Event::MouseMoved { x: 1, y: 2 },
+                Event::KeyReleased(Key::A),
+                Event::KeyPressed(Key::D),
+                Event::MousePressed(MouseButton::Left),
+            ]
+        );
+        assert_eq!(after.changes(&after), []);
+    }
+}
@@ -0,0 +1,141 @@
+//! Recent ticks, kept so the runner can travel back in time.
+//!
+//! A whole game state is one `u64`, so remembering minutes of play costs next to nothing. Each
+//! entry keeps the state and input going into a tick and the events it saw, which is enough to
+//! run that tick again and get back exactly the state and frame it produced.
+
+use std::collections::VecDeque;
+
+use super::{Event, Held};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub tick: u64,
+    /// State before the tick ran.
+    pub state: u64,
+    pub held: Held,
+    pub events: Vec<Event>,
+}
+
+pub struct History {
+    entries: VecDeque<Entry>,
+    /// How many entries are "in the past". Equal to `entries.len()` unless we have gone back.
+    cursor: usize,
+    capacity: usize,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            cursor: 0,
+            capacity,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Whether we have gone back in time, and the future is still there to go forward to.
+    pub fn is_rewound(&self) -> bool {
+        self.cursor < self.entries.len()
+    }
+
+    /// Remember a tick. If we had gone back, the old future is forgotten.
+    pub fn push(&mut self, entry: Entry) {
+        self.entries.truncate(self.cursor);
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+        self.cursor = self.entries.len();
+    }
+
+    /// Forget everything, for when the game jumps somewhere history didn't lead.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.cursor = 0;
+    }
+
+    /// Move `delta` ticks forward or back, staying within what we remember. Returns the tick
+    /// that has to run again to get to the new point in time, or `None` if we didn't move.
+    pub fn seek(&mut self, delta: isize) -> Option<&Entry> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        let cursor = self
+            .cursor
+            .saturating_add_signed(delta)
+            .clamp(1, self.entries.len());
+        if cursor == self.cursor {
+            return None;
+        }
+        self.cursor = cursor;
+        self.entries.get(cursor - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(tick: u64) -> Entry {
+        Entry {
+            tick,
+            state: tick * 10,
+            held: Held::default(),
+            events: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn seeking() {
+        let mut history = History::new(100);
+        assert_eq!(history.seek(-1), None);
+
+        for tick in 0..10 {
+            history.push(entry(tick));
+        }
+        assert!(!history.is_rewound());
+        assert_eq!(history.seek(1), None);
+
+        assert_eq!(history.seek(-3), Some(&entry(6)));
+        assert!(history.is_rewound());
+        assert_eq!(history.seek(-100), Some(&entry(0)));
+        assert_eq!(history.seek(-1), None);
+        assert_eq!(history.seek(100), Some(&entry(9)));
+        assert!(!history.is_rewound());
+    }
+
+    #[test]
+    fn pushing_after_rewinding_forgets_the_future() {
+        let mut history = History::new(100);
+        for tick in 0..10 {
+            history.push(entry(tick));
+        }
+        history.seek(-5);
+        history.push(entry(5));
+        assert_eq!(history.len(), 6);
+        assert!(!history.is_rewound());
+        assert_eq!(history.seek(-1), Some(&entry(4)));
+    }
+
+    #[test]
+    fn capacity() {
+        let mut history = History::new(4);
+        for tick in 0..10 {
+            history.push(entry(tick));
+        }
+        assert_eq!(history.len(), 4);
+        assert_eq!(history.seek(-10), Some(&entry(6)));
+    }
+}
@@ -0,0 +1,369 @@
+//! Just enough image formats to get frames out of the crate and sprites into it.
+//!
+//! PPM because it is trivial to read and write, PNG because everything can open it. The PNG
+//! encoder doesn't compress, it stores, which keeps it small and the files big.
+//!
+//! Sprites are PPMs too, usually embedded with `include_bytes!`. The plain text flavour (`P3`)
+//! is handy for those since it can be edited and reviewed like code.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use super::{Color, Frame, invalid};
+
+/// A decoded picture, for blitting with `Output::image`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    /// Row by row.
+    pub pixels: Vec<Color>,
+}
+
+impl Image {
+    pub fn from_ppm(bytes: &[u8]) -> io::Result<Self> {
+        let (width, height, rgb) = read_ppm(bytes)?;
+        let pixels = rgb
+            .chunks_exact(3)
+            .map(|rgb| Color::rgb(rgb[0], rgb[1], rgb[2]))
+            .collect();
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+}
+
+/// How to draw an `Image`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Blit {
+    /// Pixels of exactly this color (alpha ignored) are skipped.
+    pub key: Option<Color>,
+    /// Mirror left to right.
+    pub flip_x: bool,
+    /// Mirror top to bottom.
+    pub flip_y: bool,
+    /// Every image pixel becomes a `scale` by `scale` square.
+    pub scale: u32,
+}
+
+impl Default for Blit {
+    fn default() -> Self {
+        Self {
+            key: None,
+            flip_x: false,
+            flip_y: false,
+            scale: 1,
+        }
+    }
+}
+
+impl Frame {
+    /// 8-bit RGB, row by row.
+    pub fn to_rgb(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|pixel| {
+                let [b, g, r, _] = pixel.to_le_bytes();
+                [r, g, b]
+            })
+            .collect()
+    }
+
+    pub fn from_rgb(width: usize, height: usize, rgb: &[u8]) -> Self {
+        assert_eq!(rgb.len(), width * height * 3);
+        let pixels = rgb
+            .chunks_exact(3)
+            .map(|rgb| u32::from_le_bytes([rgb[2], rgb[1], rgb[0], 255]))
+            .collect();
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn write_ppm(&self, w: impl Write) -> io::Result<()> {
+        write_ppm(w, self.width, self.height, &self.to_rgb())
+    }
+
+    pub fn write_png(&self, w: impl Write) -> io::Result<()> {
+        write_png(w, self.width, self.height, &self.to_rgb())
+    }
+
+    /// Save as PPM or PNG depending on the extension of `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut out = BufWriter::new(File::create(path)?);
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("ppm") => self.write_ppm(&mut out)?,
+            Some("png") => self.write_png(&mut out)?,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("don't know how to save {}", path.display()),
+                ));
+            }
+        }
+        out.flush()
+    }
+
+    pub fn read_ppm(bytes: &[u8]) -> io::Result<Self> {
+        let (width, height, rgb) = read_ppm(bytes)?;
+        Ok(Self::from_rgb(width, height, &rgb))
+    }
+}
+
+pub fn write_ppm(mut w: impl Write, width: usize, height: usize, rgb: &[u8]) -> io::Result<()> {
+    write!(w, "P6\n{width} {height}\n255\n")?;
+    w.write_all(rgb)
+}
+
+/// Reads binary (`P6`) and plain (`P3`) PPMs with a maxval of 255. Returns width, height and
+/// RGB bytes.
+pub fn read_ppm(bytes: &[u8]) -> io::Result<(usize, usize, Vec<u8>)> {
+    let mut rest = bytes;
+    let magic = ppm_token(&mut rest)?;
+    if magic != "P6" && magic != "P3" {
+        return Err(invalid(format!("expected a P3 or P6 PPM, found {magic:?}")));
+    }
+    let width = ppm_number(&mut rest)?;
+    let height = ppm_number(&mut rest)?;
+    let maxval = ppm_number(&mut rest)?;
+    if maxval != 255 {
+        return Err(invalid(format!("unsupported maxval {maxval}")));
+    }
+    let len = width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(3))
+        .ok_or_else(|| invalid(format!("{width}x{height} is too big")))?;
+
+    if magic == "P3" {
+        let rgb = (0..len)
+            .map(|_| {
+                let token = ppm_token(&mut rest)?;
+                token
+                    .parse()
+                    .map_err(|_| invalid(format!("expected a sample, found {token:?}")))
+            })
+            .collect::<io::Result<_>>()?;
+        return Ok((width, height, rgb));
+    }
+
+    // exactly one whitespace byte separates the header from the pixels
+    let rgb = rest
+        .get(1..)
+        .and_then(|rest| rest.get(..len))
+        .ok_or_else(|| invalid("truncated PPM"))?;
+    Ok((width, height, rgb.to_vec()))
+}
+
+fn ppm_token<'a>(rest: &mut &'a [u8]) -> io::Result<&'a str> {
+    loop {
+        let skip = rest.iter().take_while(|b| b.is_ascii_whitespace()).count();
+        *rest = &rest[skip..];
+        if rest.first() == Some(&b'#') {
+            let line = rest.iter().take_while(|&&b| b != b'\n').count();
+            *rest = &rest[line..];
+        } else {
+            break;
+        }
+    }
+    let len = rest.iter().take_while(|b| !b.is_ascii_whitespace()).count();
+    let (token, tail) = rest.split_at(len);
+    *rest = tail;
+    std::str::from_utf8(token).map_err(|_| invalid("PPM header isn't ASCII"))
+}
+
+fn ppm_number(rest: &mut &[u8]) -> io::Result<usize> {
+    let token = ppm_token(rest)?;
+    token
+        .parse()
+        .map_err(|_| invalid(format!("expected a number in PPM header, found {token:?}")))
+}
+
+/// PNGs can't be empty, so `width` and `height` must not be zero.
+pub fn write_png(mut w: impl Write, width: usize, height: usize, rgb: &[u8]) -> io::Result<()> {
+    assert_eq!(rgb.len(), width * height * 3);
+    if width == 0 || height == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("can't write a {width}x{height} PNG"),
+        ));
+    }
+
+    w.write_all(b"\x89PNG\r\n\x1a\n")?;
+
+    let mut ihdr = Vec::new();
+    ihdr.extend((width as u32).to_be_bytes());
+    ihdr.extend((height as u32).to_be_bytes());
+    // 8 bits per channel, RGB, deflate, adaptive filtering, no interlacing
+    ihdr.extend([8, 2, 0, 0, 0]);
+    png_chunk(&mut w, b"IHDR", &ihdr)?;
+
+    // every scanline starts with its filter type, we always pick "none"
+    let mut raw = Vec::with_capacity(height * (width * 3 + 1));
+    for row in rgb.chunks_exact(width * 3) {
+        raw.push(0);
+        raw.extend(row);
+    }
+    png_chunk(&mut w, b"IDAT", &zlib_stored(&raw))?;
+
+    png_chunk(&mut w, b"IEND", &[])
+}
+
+fn png_chunk(w: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    w.write_all(&(data.len() as u32).to_be_bytes())?;
+    w.write_all(kind)?;
+    w.write_all(data)?;
+    let crc = crc32(kind.iter().chain(data));
+    w.write_all(&crc.to_be_bytes())
+}
+
+/// A zlib stream made of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(u16::MAX as usize).peekable();
+    if blocks.peek().is_none() {
+        out.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(last as u8);
+        out.extend(len.to_le_bytes());
+        out.extend((!len).to_le_bytes());
+        out.extend(block);
+    }
+    out.extend(adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32<'a>(data: impl IntoIterator<Item = &'a u8>) -> u32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0; 256];
+        let mut n = 0;
+        while n < 256 {
+            let mut c = n as u32;
+            let mut k = 0;
+            while k < 8 {
+                c = if c & 1 == 1 {
+                    0xedb8_8320 ^ (c >> 1)
+                } else {
+                    c >> 1
+                };
+                k += 1;
+            }
+            table[n] = c;
+            n += 1;
+        }
+        table
+    };
+
+    let mut crc = !0u32;
+    for &byte in data {
+        crc = TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checkerboard() -> Frame {
+        let mut frame = Frame::new(5, 3);
+        for (i, pixel) in frame.pixels.iter_mut().enumerate() {
+            *pixel = if i % 2 == 0 { 0xff12_3456 } else { 0xff00_ff00 };
+        }
+        frame
+    }
+
+    #[test]
+    fn ppm_roundtrip() {
+        let frame = checkerboard();
+        let mut bytes = Vec::new();
+        frame.write_ppm(&mut bytes).unwrap();
+        assert!(bytes.starts_with(b"P6\n5 3\n255\n"));
+        assert_eq!(Frame::read_ppm(&bytes).unwrap(), frame);
+    }
+
+    #[test]
+    fn ppm_comments() {
+        let bytes = b"P6 # comment\n# another\n1 1 255\n\x01\x02\x03";
+        assert_eq!(read_ppm(bytes).unwrap(), (1, 1, vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn plain_ppm() {
+        let image = Image::from_ppm(
+            b"P3\n# a sprite\n2 2\n255\n\
+              255 0 0    0 255 0\n\
+              0 0 255    1 2 3\n",
+        )
+        .unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(image.get(1, 0), Color::rgb(0, 255, 0));
+        assert_eq!(image.get(1, 1), Color::rgb(1, 2, 3));
+
+        assert!(Image::from_ppm(b"P3 1 1 255 1 2").is_err());
+        assert!(Image::from_ppm(b"P3 1 1 255 1 2 256").is_err());
+    }
+
+    #[test]
+    fn bad_sizes() {
+        let huge = format!("P6 {} {} 255\n", usize::MAX / 2, 3);
+        let err = read_ppm(huge.as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(read_ppm(b"P6 2 2 255\n\x01\x02\x03").is_err());
+
+        let empty = Frame::new(0, 3);
+        assert!(empty.write_png(&mut Vec::new()).is_err());
+        // PPMs can be empty
+        let mut bytes = Vec::new();
+        empty.write_ppm(&mut bytes).unwrap();
+        assert_eq!(Frame::read_ppm(&bytes).unwrap(), empty);
+    }
+
+    #[test]
+    fn png_structure() {
+        let mut bytes = Vec::new();
+        checkerboard().write_png(&mut bytes).unwrap();
+        assert!(bytes.starts_with(b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR"));
+        // the IEND chunk is always the same, CRC included
+        assert!(bytes.ends_with(b"\0\0\0\0IEND\xae\x42\x60\x82"));
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn stored_blocks() {
+        let data = vec![7; u16::MAX as usize + 10];
+        let zlib = zlib_stored(&data);
+        // header, two block headers, data, checksum
+        assert_eq!(zlib.len(), 2 + 5 * 2 + data.len() + 4);
+        assert_eq!(zlib[2], 0);
+        assert_eq!(zlib[2 + 5 + u16::MAX as usize], 1);
+    }
+}
@@ -0,0 +1,75 @@
+//! Keys, independent of whichever backend produced them.
+//!
+//! The list mirrors minifb's so existing games keep compiling, but nothing here depends on it.
+
+/// Invokes `$callback!` with the name of every key. Backends use this to map their own key
+/// type onto `Key` without repeating the list.
+macro_rules! for_each_key {
+    ($callback:ident) => {
+        $callback! {
+            Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9,
+            A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+            F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, F13, F14, F15,
+            Down, Left, Right, Up,
+            Apostrophe, Backquote, Backslash, Comma, Equal, LeftBracket, Minus, Period,
+            RightBracket, Semicolon, Slash,
+            Backspace, Delete, End, Enter, Escape, Home, Insert, Menu, PageDown, PageUp, Pause,
+            Space, Tab, NumLock, CapsLock, ScrollLock,
+            LeftShift, RightShift, LeftCtrl, RightCtrl, LeftAlt, RightAlt, LeftSuper, RightSuper,
+            NumPad0, NumPad1, NumPad2, NumPad3, NumPad4, NumPad5, NumPad6, NumPad7, NumPad8,
+            NumPad9, NumPadDot, NumPadSlash, NumPadAsterisk, NumPadMinus, NumPadPlus, NumPadEnter,
+        }
+    };
+}
+#[cfg(feature = "minifb")]
+pub(crate) use for_each_key;
+
+macro_rules! declare_keys {
+    ($($key:ident),* $(,)?) => {
+        #[repr(u8)]
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub enum Key {
+            $($key),*
+        }
+
+        impl Key {
+            /// Every key, `Key::ALL[key as usize] == key`.
+            pub const ALL: &'static [Key] = &[$(Key::$key),*];
+
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Key::$key => stringify!($key)),*
+                }
+            }
+
+            /// The key called `name`, ignoring case.
+            pub fn from_name(name: &str) -> Option<Key> {
+                Key::ALL.iter().copied().find(|key| key.name().eq_ignore_ascii_case(name))
+            }
+        }
+    };
+}
+for_each_key!(declare_keys);
+
+/// How held keys repeat, see `Game::KEY_REPEAT`. Both are in ticks, so repeats are as
+/// deterministic as everything else.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KeyRepeat {
+    /// From the press to the first repeat. At least 1.
+    pub delay: u32,
+    /// Between repeats after that. At least 1.
+    pub interval: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_is_indexed_by_discriminant() {
+        for (i, key) in Key::ALL.iter().enumerate() {
+            assert_eq!(*key as usize, i, "{}", key.name());
+            assert_eq!(Key::from_name(&key.name().to_lowercase()), Some(*key));
+        }
+    }
+}
@@ -0,0 +1,26 @@
+//! Mouse buttons, independent of whichever backend produced them.
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+}
+
+impl MouseButton {
+    /// Every button, `ALL[button as usize] == button`.
+    pub const ALL: &[MouseButton] = &[MouseButton::Left, MouseButton::Middle, MouseButton::Right];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_is_indexed_by_discriminant() {
+        for (index, button) in MouseButton::ALL.iter().enumerate() {
+            assert_eq!(*button as usize, index);
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! Command line options understood by `Game::run`.
+
+use std::path::PathBuf;
+
+use super::saves::parse_state;
+use crate::password;
+
+const USAGE: &str = "\
+options:
+    --record <path>    write every tick's input to <path>
+    --replay <path>    play back a recording made with --record, then hand over to the player
+    --state <state>    start from this state instead of the game's own, in decimal, 0x hex
+                       or 0b binary
+    --code <password>  start from the state behind a password shown while paused
+    --bindings <path>  read key bindings from <path>, see `game::action` for the format
+    --scale <n>        start with every game pixel <n> by <n> screen pixels
+    --fullscreen       start fullscreen, F11 switches back
+    --backend <name>   `window`, or `terminal` to play in this terminal, over SSH say";
+
+/// Where `Game::run` plays.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BackendKind {
+    #[cfg(feature = "minifb")]
+    Window,
+    Terminal,
+}
+
+impl Default for BackendKind {
+    /// A window if this build can open one.
+    fn default() -> Self {
+        #[cfg(feature = "minifb")]
+        return Self::Window;
+        #[cfg(not(feature = "minifb"))]
+        return Self::Terminal;
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Options {
+    pub record: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub state: Option<u64>,
+    pub bindings: Option<PathBuf>,
+    pub scale: Option<u32>,
+    pub fullscreen: bool,
+    pub backend: BackendKind,
+}
+
+impl Options {
+    /// Parse `std::env::args`, exiting with a usage message if they don't make sense.
+    pub fn from_args() -> Self {
+        match Self::parse(std::env::args().skip(1)) {
+            Ok(options) => options,
+            Err(err) => {
+                eprintln!("{err}\n\n{USAGE}");
+                std::process::exit(2)
+            }
+        }
+    }
+
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+        let mut seen = Vec::new();
+        while let Some(arg) = args.next() {
+            if seen.contains(&arg) {
+                return Err(format!("{arg} is given more than once"));
+            }
+            seen.push(arg.clone());
+            let mut value = || args.next().ok_or(format!("{arg} expects a value"));
+            match arg.as_str() {
+                "--record" => options.record = Some(value()?.into()),
+                "--replay" => options.replay = Some(value()?.into()),
+                "--bindings" => options.bindings = Some(value()?.into()),
+                "--scale" => {
+                    let scale = value()?;
+                    let scale = scale.parse().ok().filter(|&scale| scale > 0);
+                    options.scale = Some(scale.ok_or("--scale expects a whole number above 0")?);
+                }
+                "--fullscreen" => options.fullscreen = true,
+                "--backend" => {
+                    options.backend = match value()?.as_str() {
+                        #[cfg(feature = "minifb")]
+                        "window" => BackendKind::Window,
+                        #[cfg(not(feature = "minifb"))]
+                        "window" => return Err("built without window support".to_owned()),
+                        "terminal" => BackendKind::Terminal,
+                        other => return Err(format!("no backend called {other:?}")),
+                    }
+                }
+                "--state" | "--code" if options.state.is_some() => {
+                    return Err("--state and --code both pick where to start".to_owned());
+                }
+                "--state" => options.state = Some(parse_state(&value()?)?),
+                "--code" => {
+                    let code = value()?;
+                    let state = password::decode(&code)
+                        .map_err(|err| format!("bad password {code:?}: {err}"))?;
+                    options.state = Some(state);
+                }
+                _ => return Err(format!("unexpected argument {arg:?}")),
+            }
+        }
+        if options.replay.is_some() && options.state.is_some() {
+            return Err("--replay can't be combined with --state or --code".to_owned());
+        }
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses() {
+        assert_eq!(parse(&[]), Ok(Options::default()));
+        assert_eq!(
+            parse(&["--replay", "a.rec", "--record", "b.rec"]),
+            Ok(Options {
+                record: Some("b.rec".into()),
+                replay: Some("a.rec".into()),
+                state: None,
+                bindings: None,
+                scale: None,
+                fullscreen: false,
+                backend: BackendKind::default(),
+            })
+        );
+        assert_eq!(
+            parse(&["--backend", "terminal"]).unwrap().backend,
+            BackendKind::Terminal
+        );
+        assert!(parse(&["--backend", "paper"]).is_err());
+        let options = parse(&["--scale", "3", "--fullscreen"]).unwrap();
+        assert_eq!((options.scale, options.fullscreen), (Some(3), true));
+        assert!(parse(&["--scale", "0"]).is_err());
+        assert_eq!(parse(&["--state", "0x10"]).unwrap().state, Some(16));
+        assert!(parse(&["--state", "ten"]).is_err());
+        assert!(parse(&["--state", "1", "--replay", "a.rec"]).is_err());
+        assert_eq!(
+            parse(&["--code", "028t 5cy4 tqkf fa"]).unwrap().state,
+            Some(0x0123_4567_89ab_cdef)
+        );
+        assert!(parse(&["--code", "028T-5CY4-TQKF-F0"]).is_err());
+        assert_eq!(
+            parse(&["--state", "1", "--code", "0000-0000-0000-00"]),
+            Err("--state and --code both pick where to start".to_owned())
+        );
+        assert_eq!(
+            parse(&["--state", "1", "--state", "2"]),
+            Err("--state is given more than once".to_owned())
+        );
+        assert!(parse(&["--fullscreen", "--fullscreen"]).is_err());
+        assert!(parse(&["--record"]).is_err());
+        assert!(parse(&["--bogus"]).is_err());
+    }
+}
@@ -0,0 +1,599 @@
+//! What a game draws during a tick, and how that becomes pixels.
+//!
+//! Shapes are collected during `Game::tick` and rasterized afterwards. Anything outside the
+//! frame is clipped, so games are free to draw partially (or entirely) off screen.
+
+use std::marker::PhantomData;
+
+use super::{
+    Game,
+    color::{Blend, Color},
+    font,
+    image::{Blit, Image},
+};
+
+pub struct Output<'a, G: Game> {
+    shapes: Vec<(Blend, Shape<'a>)>,
+    blend: Blend,
+    phantom: PhantomData<&'a mut G>,
+}
+
+enum Shape<'a> {
+    Rect {
+        x: i32,
+        y: i32,
+        w: u32,
+        h: u32,
+        color: Color,
+    },
+    Pixel {
+        x: i32,
+        y: i32,
+        color: Color,
+    },
+    Line {
+        from: [i32; 2],
+        to: [i32; 2],
+        color: Color,
+    },
+    Ellipse {
+        center: [i32; 2],
+        radii: [u32; 2],
+        filled: bool,
+        color: Color,
+    },
+    Polygon {
+        points: Vec<[i32; 2]>,
+        filled: bool,
+        color: Color,
+    },
+    Text {
+        x: i32,
+        y: i32,
+        text: String,
+        scale: u32,
+        color: Color,
+    },
+    Image {
+        x: i32,
+        y: i32,
+        image: &'a Image,
+        blit: Blit,
+    },
+}
+
+impl<'a, G: Game> Output<'a, G> {
+    pub fn rect(&mut self, x: i32, y: i32, w: u32, h: u32, color: Color) {
+        self.push(Shape::Rect { x, y, w, h, color });
+    }
+
+    pub fn rect_outline(&mut self, x: i32, y: i32, w: u32, h: u32, color: Color) {
+        if w == 0 || h == 0 {
+            return;
+        }
+        let [x1, y1] = [x + w as i32 - 1, y + h as i32 - 1];
+        self.polygon_outline(&[[x, y], [x1, y], [x1, y1], [x, y1]], color);
+    }
+
+    pub fn pixel(&mut self, x: i32, y: i32, color: Color) {
+        self.push(Shape::Pixel { x, y, color });
+    }
+
+    /// A one pixel wide line including both end points.
+    pub fn line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: Color) {
+        self.push(Shape::Line {
+            from: [x0, y0],
+            to: [x1, y1],
+            color,
+        });
+    }
+
+    pub fn circle(&mut self, x: i32, y: i32, r: u32, color: Color) {
+        self.ellipse(x, y, r, r, color);
+    }
+
+    pub fn circle_outline(&mut self, x: i32, y: i32, r: u32, color: Color) {
+        self.ellipse_outline(x, y, r, r, color);
+    }
+
+    /// An ellipse centered on pixel `(x, y)`, `2 * rx + 1` pixels wide and `2 * ry + 1` tall.
+    pub fn ellipse(&mut self, x: i32, y: i32, rx: u32, ry: u32, color: Color) {
+        self.push(Shape::Ellipse {
+            center: [x, y],
+            radii: [rx, ry],
+            filled: true,
+            color,
+        });
+    }
+
+    pub fn ellipse_outline(&mut self, x: i32, y: i32, rx: u32, ry: u32, color: Color) {
+        self.push(Shape::Ellipse {
+            center: [x, y],
+            radii: [rx, ry],
+            filled: false,
+            color,
+        });
+    }
+
+    pub fn triangle(&mut self, a: [i32; 2], b: [i32; 2], c: [i32; 2], color: Color) {
+        self.polygon(&[a, b, c], color);
+    }
+
+    pub fn triangle_outline(&mut self, a: [i32; 2], b: [i32; 2], c: [i32; 2], color: Color) {
+        self.polygon_outline(&[a, b, c], color);
+    }
+
+    /// Filled using the even-odd rule. Like `rect`, points are pixel corners: a polygon
+    /// through `[0, 0]`, `[2, 0]`, `[2, 2]` and `[0, 2]` covers four pixels.
+    pub fn polygon(&mut self, points: &[[i32; 2]], color: Color) {
+        self.push(Shape::Polygon {
+            points: points.to_vec(),
+            filled: true,
+            color,
+        });
+    }
+
+    /// Lines between consecutive points, closing back to the first.
+    pub fn polygon_outline(&mut self, points: &[[i32; 2]], color: Color) {
+        self.push(Shape::Polygon {
+            points: points.to_vec(),
+            filled: false,
+            color,
+        });
+    }
+
+    /// Draw `text` with the built-in font, `(x, y)` being the top left corner. Each font pixel
+    /// becomes a `scale` by `scale` square. See `font::text_size` for how much room it takes.
+    pub fn text(&mut self, x: i32, y: i32, text: &str, scale: u32, color: Color) {
+        self.push(Shape::Text {
+            x,
+            y,
+            text: text.to_owned(),
+            scale,
+            color,
+        });
+    }
+
+    /// Draw `value` in decimal, padded with zeros to at least `digits` digits.
+    pub fn number(&mut self, x: i32, y: i32, value: u64, digits: usize, scale: u32, color: Color) {
+        self.text(x, y, &format!("{value:0digits$}"), scale, color);
+    }
+
+    /// Draw `image` with its top left corner at `(x, y)`. The image's own alpha is respected.
+    pub fn image(&mut self, x: i32, y: i32, image: &'a Image, blit: Blit) {
+        self.push(Shape::Image { x, y, image, blit });
+    }
+
+    /// How everything drawn from now on combines with what's below it.
+    pub fn set_blend(&mut self, blend: Blend) {
+        self.blend = blend;
+    }
+
+    fn push(&mut self, shape: Shape<'a>) {
+        self.shapes.push((self.blend, shape));
+    }
+
+    pub(crate) fn new() -> Self {
+        Self {
+            shapes: Vec::new(),
+            blend: Blend::Normal,
+            phantom: PhantomData,
+        }
+    }
+
+    pub(crate) fn write_to(self, buf: &mut [u32]) {
+        let mut canvas = Canvas {
+            buf,
+            width: G::WIDTH as i64,
+            height: G::HEIGHT as i64,
+            blend: Blend::Normal,
+        };
+        for (blend, shape) in self.shapes {
+            canvas.blend = blend;
+            match shape {
+                Shape::Rect { x, y, w, h, color } => {
+                    canvas.rect(x.into(), y.into(), w.into(), h.into(), color)
+                }
+                Shape::Pixel { x, y, color } => canvas.plot(x.into(), y.into(), color),
+                Shape::Line { from, to, color } => canvas.line(from, to, color),
+                Shape::Ellipse {
+                    center,
+                    radii,
+                    filled,
+                    color,
+                } => canvas.ellipse(center, radii, filled, color),
+                Shape::Polygon {
+                    points,
+                    filled: true,
+                    color,
+                } => canvas.fill_polygon(&points, color),
+                Shape::Polygon {
+                    points,
+                    filled: false,
+                    color,
+                } => {
+                    for (i, &from) in points.iter().enumerate() {
+                        canvas.line(from, points[(i + 1) % points.len()], color);
+                    }
+                }
+                Shape::Text {
+                    x,
+                    y,
+                    text,
+                    scale,
+                    color,
+                } => canvas.text(x.into(), y.into(), &text, scale.into(), color),
+                Shape::Image { x, y, image, blit } => canvas.image(x.into(), y.into(), image, blit),
+            }
+        }
+    }
+}
+
+/// A frame buffer being drawn on. Coordinates are `i64` so nothing a game passes in can
+/// overflow, and every drawing operation clips before it loops.
+struct Canvas<'b> {
+    buf: &'b mut [u32],
+    width: i64,
+    height: i64,
+    blend: Blend,
+}
+
+impl Canvas<'_> {
+    fn plot(&mut self, x: i64, y: i64, color: Color) {
+        if (0..self.width).contains(&x) && (0..self.height).contains(&y) {
+            let pixel = &mut self.buf[(y * self.width + x) as usize];
+            *pixel = self
+                .blend
+                .apply(Color::from_pixel(*pixel), color)
+                .to_pixel();
+        }
+    }
+
+    /// Row `y` from `x0` to `x1`, inclusive.
+    fn span(&mut self, y: i64, x0: i64, x1: i64, color: Color) {
+        if !(0..self.height).contains(&y) {
+            return;
+        }
+        for x in x0.max(0)..=x1.min(self.width - 1) {
+            self.plot(x, y, color);
+        }
+    }
+
+    fn rect(&mut self, x: i64, y: i64, w: i64, h: i64, color: Color) {
+        for row in y.max(0)..(y + h).min(self.height) {
+            self.span(row, x, x + w - 1, color);
+        }
+    }
+
+    fn text(&mut self, x: i64, y: i64, text: &str, scale: i64, color: Color) {
+        let advance = i64::from(font::ADVANCE) * scale;
+        let line_height = i64::from(font::LINE_HEIGHT) * scale;
+        for (row, line) in text.split('\n').enumerate() {
+            let top = y + row as i64 * line_height;
+            if top >= self.height {
+                break;
+            }
+            for (col, c) in line.chars().enumerate() {
+                let left = x + col as i64 * advance;
+                if left >= self.width {
+                    break;
+                }
+                for (j, bits) in font::glyph(c).iter().enumerate() {
+                    for i in 0..font::GLYPH_WIDTH {
+                        if bits >> (font::GLYPH_WIDTH - 1 - i) & 1 == 1 {
+                            let [px, py] = [left + i64::from(i) * scale, top + j as i64 * scale];
+                            self.rect(px, py, scale, scale, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn image(&mut self, x: i64, y: i64, image: &Image, blit: Blit) {
+        let scale = i64::from(blit.scale);
+        for j in 0..image.height {
+            let top = y + j as i64 * scale;
+            let sy = if blit.flip_y { image.height - 1 - j } else { j };
+            for i in 0..image.width {
+                let sx = if blit.flip_x { image.width - 1 - i } else { i };
+                let color = image.get(sx, sy);
+                if blit.key.is_some_and(|key| key.with_alpha(color.a) == color) {
+                    continue;
+                }
+                self.rect(x + i as i64 * scale, top, scale, scale, color);
+            }
+        }
+    }
+
+    fn line(&mut self, from: [i32; 2], to: [i32; 2], color: Color) {
+        let [x0, y0] = from.map(i64::from);
+        let [x1, y1] = to.map(i64::from);
+        if (x1 - x0).abs() >= (y1 - y0).abs() {
+            self.line_along_x([x0, y0], [x1, y1], color, false);
+        } else {
+            self.line_along_x([y0, x0], [y1, x1], color, true);
+        }
+    }
+
+    /// Steps along the major axis, which is `x` unless `transposed`. Only the part of the
+    /// major axis that is on screen is visited.
+    fn line_along_x(&mut self, a: [i64; 2], b: [i64; 2], color: Color, transposed: bool) {
+        let ([x0, y0], [x1, y1]) = if a[0] <= b[0] { (a, b) } else { (b, a) };
+        let (dx, dy) = (x1 - x0, y1 - y0);
+        let major_len = if transposed { self.height } else { self.width };
+        for x in x0.max(0)..=x1.min(major_len - 1) {
+            let y = if dx == 0 {
+                y0
+            } else {
+                // i128 because a line across the whole i32 range overflows i64 here
+                let (i, dx, dy) = (i128::from(x - x0), i128::from(dx), i128::from(dy));
+                y0 + (2 * i * dy + dx).div_euclid(2 * dx) as i64
+            };
+            if transposed {
+                self.plot(y, x, color);
+            } else {
+                self.plot(x, y, color);
+            }
+        }
+    }
+
+    fn ellipse(&mut self, center: [i32; 2], radii: [u32; 2], filled: bool, color: Color) {
+        let [cx, cy] = center.map(i64::from);
+        let [rx, ry] = radii.map(i64::from);
+
+        // half the width of row `dy`, pixels count as inside when their center is
+        let half_width = |dy: i64| {
+            (dy.abs() <= ry).then(|| {
+                let t = dy as f64 / (ry as f64 + 0.5);
+                ((rx as f64 + 0.5) * (1.0 - t * t).sqrt()).floor() as i64
+            })
+        };
+
+        for y in (cy - ry).max(0)..=(cy + ry).min(self.height - 1) {
+            let dy = y - cy;
+            let Some(w) = half_width(dy) else { continue };
+            if filled {
+                self.span(y, cx - w, cx + w, color);
+                continue;
+            }
+            // skip pixels whose four neighbours are all inside
+            let interior = match (half_width(dy - 1), half_width(dy + 1)) {
+                (Some(above), Some(below)) => (w - 1).min(above).min(below),
+                _ => -1,
+            };
+            self.span(y, cx - w, cx - interior - 1, color);
+            self.span(y, cx + interior + 1, cx + w, color);
+        }
+    }
+
+    fn fill_polygon(&mut self, points: &[[i32; 2]], color: Color) {
+        let Some(top) = points.iter().map(|p| i64::from(p[1])).min() else {
+            return;
+        };
+        let bottom = points.iter().map(|p| i64::from(p[1])).max().unwrap();
+
+        let mut crossings = Vec::new();
+        for y in top.max(0)..bottom.min(self.height) {
+            // sample through the middle of the row
+            let sy = y as f64 + 0.5;
+            crossings.clear();
+            for (i, a) in points.iter().enumerate() {
+                let b = points[(i + 1) % points.len()];
+                let [ax, ay] = a.map(f64::from);
+                let [bx, by] = b.map(f64::from);
+                if (ay <= sy) != (by <= sy) {
+                    crossings.push(ax + (sy - ay) / (by - ay) * (bx - ax));
+                }
+            }
+            crossings.sort_by(f64::total_cmp);
+            for pair in crossings.chunks_exact(2) {
+                // pixels whose centers lie within [pair[0], pair[1])
+                let x0 = (pair[0] - 0.5).ceil() as i64;
+                let x1 = (pair[1] - 0.5).ceil() as i64 - 1;
+                self.span(y, x0, x1, color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Frame, test_games::Tiny};
+
+    const W: Color = Color::WHITE;
+
+    fn render<'a>(f: impl FnOnce(&mut Output<'a, Tiny>)) -> Frame {
+        let mut output = Output::new();
+        f(&mut output);
+        let mut frame = Frame::new(Tiny::WIDTH, Tiny::HEIGHT);
+        output.write_to(&mut frame.pixels);
+        frame
+    }
+
+    /// Rasterize and return the frame as text, `#` for anything that isn't black.
+    fn draw<'a>(f: impl FnOnce(&mut Output<'a, Tiny>)) -> Vec<String> {
+        render(f)
+            .pixels
+            .chunks(Tiny::WIDTH)
+            .map(|row| {
+                row.iter()
+                    .map(|&p| if p & 0xff_ffff == 0 { '.' } else { '#' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn edges_are_drawn() {
+        let frame = draw(|o| {
+            o.pixel(0, 0, W);
+            o.pixel(11, 7, W);
+            o.rect(-2, 3, 3, 1, W);
+        });
+        assert_eq!(frame[0], "#...........");
+        assert_eq!(frame[3], "#...........");
+        assert_eq!(frame[7], "...........#");
+    }
+
+    #[test]
+    fn far_off_screen_is_clipped_quickly() {
+        let frame = draw(|o| {
+            o.line(i32::MIN, i32::MIN, i32::MAX, i32::MAX, W);
+            o.rect(i32::MIN, i32::MIN, u32::MAX, u32::MAX, W);
+            o.circle(i32::MAX, 0, u32::MAX, W);
+            o.polygon(&[[i32::MIN, 0], [i32::MAX, 0], [0, i32::MAX]], W);
+        });
+        assert!(frame.iter().all(|row| row.contains('#')));
+    }
+
+    #[test]
+    fn lines() {
+        let frame = draw(|o| {
+            o.line(1, 1, 7, 4, W);
+            o.line(10, 0, 10, 0, W);
+            o.line(11, 7, 11, 5, W);
+        });
+        assert_eq!(
+            frame,
+            [
+                "..........#.",
+                ".#..........",
+                "..##........",
+                "....##......",
+                "......##....",
+                "...........#",
+                "...........#",
+                "...........#",
+            ]
+        );
+    }
+
+    #[test]
+    fn circles() {
+        let frame = draw(|o| {
+            o.circle(2, 2, 2, W);
+            o.circle_outline(8, 3, 3, W);
+        });
+        assert_eq!(
+            frame,
+            [
+                ".###...###..",
+                "#####.#...#.",
+                "######.....#",
+                "######.....#",
+                ".###.#.....#",
+                "......#...#.",
+                ".......###..",
+                "............",
+            ]
+        );
+    }
+
+    #[test]
+    fn text() {
+        let frame = draw(|o| o.text(0, 0, "Hi", 1, W));
+        assert_eq!(
+            frame,
+            [
+                "#...#...#...",
+                "#...#.......",
+                "#...#..##...",
+                "#####...#...",
+                "#...#...#...",
+                "#...#...#...",
+                "#...#..###..",
+                "............",
+            ]
+        );
+
+        let frame = draw(|o| o.number(0, 1, 7, 2, 1, W));
+        assert_eq!(
+            frame,
+            [
+                "............",
+                ".###..#####.",
+                "#...#.....#.",
+                "#..##....#..",
+                "#.#.#...#...",
+                "##..#..#....",
+                "#...#..#....",
+                ".###...#....",
+            ]
+        );
+    }
+
+    #[test]
+    fn blending() {
+        let frame = render(|o| {
+            o.rect(0, 0, 4, 1, Color::rgb(200, 0, 0));
+            o.rect(0, 0, 2, 1, Color::rgba(0, 0, 200, 128));
+            o.set_blend(Blend::Add);
+            o.pixel(3, 0, Color::rgb(0, 100, 0));
+            o.pixel(3, 0, Color::rgb(100, 0, 0));
+        });
+        let at = |x| Color::from_pixel(frame.get(x, 0));
+        assert_eq!(at(0), Color::rgb(100, 0, 100));
+        assert_eq!(at(2), Color::rgb(200, 0, 0));
+        assert_eq!(at(3), Color::rgb(255, 100, 0));
+        assert_eq!(at(4), Color::TRANSPARENT);
+    }
+
+    #[test]
+    fn images() {
+        let arrow = Image::from_ppm(
+            b"P3 3 2 255
+              255 255 255  255 255 255  0 0 0
+              255 255 255  0 0 0        0 0 0",
+        )
+        .unwrap();
+        let frame = draw(|o| {
+            o.image(0, 0, &arrow, Blit::default());
+            let keyed = Blit {
+                key: Some(Color::BLACK),
+                flip_x: true,
+                flip_y: true,
+                ..Blit::default()
+            };
+            o.image(4, 0, &arrow, keyed);
+            let scaled = Blit { scale: 2, ..keyed };
+            o.image(7, 2, &arrow, scaled);
+            o.image(-2, 6, &arrow, scaled);
+        });
+        assert_eq!(
+            frame,
+            [
+                "##....#.....",
+                "#....##.....",
+                "...........#",
+                "...........#",
+                ".........###",
+                ".........###",
+                "..##........",
+                "..##........",
+            ]
+        );
+    }
+
+    #[test]
+    fn polygons() {
+        let frame = draw(|o| {
+            o.triangle([0, 0], [6, 0], [0, 6], W);
+            o.rect_outline(7, 2, 5, 4, W);
+        });
+        assert_eq!(
+            frame,
+            [
+                "#####.......",
+                "####........",
+                "###....#####",
+                "##.....#...#",
+                "#......#...#",
+                ".......#####",
+                "............",
+                "............",
+            ]
+        );
+    }
+}
@@ -0,0 +1,440 @@
+//! Recording and replaying sessions.
+//!
+//! `tick` is pure and the whole state is one `u64`, so the starting state plus the events of
+//! every tick is enough to reproduce a session exactly.
+//!
+//! # Format
+//!
+//! ```text
+//! magic  b"BWRC"
+//! u8     version
+//! u64    starting state, little endian
+//! then, for every tick that had events:
+//!   varint  number of quiet ticks before it
+//!   varint  number of events (never zero)
+//!   events  a tag byte followed by its payload, see the `TAG_` constants
+//! and finally:
+//!   varint  number of quiet ticks at the end
+//!   varint  zero
+//! ```
+//!
+//! Most ticks have no events, so a session costs a few bytes per key press. Mouse movement is
+//! chattier, a few bytes per tick the mouse moves.
+//!
+//! Older versions only lack some kinds of event, so they read as is. Before version 4 there
+//! were no action events though, so games that use actions need them added from the player's
+//! bindings (see `Recording::add_actions`).
+
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use super::{Event, Held, Key, MouseButton, action::Bindings, held::MAX_ACTIONS, invalid};
+
+const MAGIC: &[u8; 4] = b"BWRC";
+const VERSION: u8 = 5;
+/// The first version with action events.
+const ACTIONS_VERSION: u8 = 4;
+
+/// Followed by the key as a byte.
+const TAG_KEY_PRESSED: u8 = 0;
+/// Followed by x and y as zigzag varints.
+const TAG_MOUSE_MOVED: u8 = 1;
+/// Followed by the button as a byte.
+const TAG_MOUSE_PRESSED: u8 = 2;
+const TAG_MOUSE_RELEASED: u8 = 3;
+/// Followed by x and y as zigzag varints.
+const TAG_SCROLLED: u8 = 4;
+/// Followed by the key as a byte.
+const TAG_KEY_RELEASED: u8 = 5;
+/// Followed by the action's index as a byte, below `MAX_ACTIONS`.
+const TAG_ACTION_PRESSED: u8 = 6;
+const TAG_ACTION_RELEASED: u8 = 7;
+/// Followed by the character as a varint.
+const TAG_CHAR: u8 = 8;
+
+/// A whole session, in memory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Recording {
+    pub init: u64,
+    /// Events for every tick, in order.
+    pub ticks: Vec<Vec<Event>>,
+    /// Whether `ticks` has the action events that go with its key events. Only recordings from
+    /// before actions existed don't.
+    pub actions: bool,
+}
+
+impl Recording {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn read(mut r: impl Read) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a recording"));
+        }
+        let version = read_u8(&mut r)?;
+        if !(1..=VERSION).contains(&version) {
+            return Err(invalid(format!("unsupported recording version {version}")));
+        }
+        let mut init = [0; 8];
+        r.read_exact(&mut init)?;
+
+        let mut ticks = Vec::new();
+        loop {
+            let gap = read_varint(&mut r)?;
+            ticks.extend((0..gap).map(|_| Vec::new()));
+            let count = read_varint(&mut r)?;
+            if count == 0 {
+                break;
+            }
+            let events = (0..count)
+                .map(|_| read_event(&mut r))
+                .collect::<io::Result<_>>()?;
+            ticks.push(events);
+        }
+
+        Ok(Self {
+            init: u64::from_le_bytes(init),
+            ticks,
+            actions: version >= ACTIONS_VERSION,
+        })
+    }
+
+    /// Add the action events a recording from before actions lacks, as `bindings` turn its keys
+    /// into actions. Does nothing if it has them already.
+    pub fn add_actions(&mut self, bindings: &Bindings) {
+        if self.actions {
+            return;
+        }
+        let mut held = Held::default();
+        for events in &mut self.ticks {
+            *events = bindings.translate(&held, events);
+            held.apply(events);
+        }
+        self.actions = true;
+    }
+
+    /// Fails if any action event is for an action past the game's `count`, which it would
+    /// never see otherwise.
+    pub fn check_actions(&self, count: usize) -> io::Result<()> {
+        let events = self.ticks.iter().flatten();
+        let mut actions = events.filter_map(|event| match *event {
+            Event::ActionPressed(action) | Event::ActionReleased(action) => Some(action),
+            _ => None,
+        });
+        match actions.find(|&action| action as usize >= count) {
+            Some(action) => Err(invalid(format!(
+                "there is no action {action}, the game has {count}"
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Always in the current version, which has actions, so call `add_actions` first if they are
+    /// missing.
+    pub fn write(&self, w: impl Write) -> io::Result<()> {
+        let mut recorder = Recorder::new(w, self.init)?;
+        for events in &self.ticks {
+            recorder.record(events)?;
+        }
+        recorder.finish()?;
+        Ok(())
+    }
+}
+
+/// Writes a recording as the session goes, so nothing is kept in memory.
+pub struct Recorder<W: Write> {
+    out: W,
+    gap: u64,
+}
+
+impl Recorder<BufWriter<File>> {
+    pub fn create(path: impl AsRef<Path>, init: u64) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?), init)
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(mut out: W, init: u64) -> io::Result<Self> {
+        out.write_all(MAGIC)?;
+        out.write_all(&[VERSION])?;
+        out.write_all(&init.to_le_bytes())?;
+        Ok(Self { out, gap: 0 })
+    }
+
+    /// Append the events of one tick.
+    pub fn record(&mut self, events: &[Event]) -> io::Result<()> {
+        if events.is_empty() {
+            self.gap += 1;
+            return Ok(());
+        }
+        write_varint(&mut self.out, self.gap)?;
+        write_varint(&mut self.out, events.len() as u64)?;
+        for event in events {
+            write_event(&mut self.out, event)?;
+        }
+        self.gap = 0;
+        Ok(())
+    }
+
+    /// Terminate the recording. Dropping a `Recorder` without calling this leaves a truncated file.
+    pub fn finish(mut self) -> io::Result<W> {
+        write_varint(&mut self.out, self.gap)?;
+        write_varint(&mut self.out, 0)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+fn write_event(w: &mut impl Write, event: &Event) -> io::Result<()> {
+    match *event {
+        Event::KeyPressed(key) => w.write_all(&[TAG_KEY_PRESSED, key as u8]),
+        Event::KeyReleased(key) => w.write_all(&[TAG_KEY_RELEASED, key as u8]),
+        Event::Char(c) => {
+            w.write_all(&[TAG_CHAR])?;
+            write_varint(w, c.into())
+        }
+        Event::ActionPressed(action) => w.write_all(&[TAG_ACTION_PRESSED, action]),
+        Event::ActionReleased(action) => w.write_all(&[TAG_ACTION_RELEASED, action]),
+        Event::MouseMoved { x, y } => {
+            w.write_all(&[TAG_MOUSE_MOVED])?;
+            write_zigzag(w, x)?;
+            write_zigzag(w, y)
+        }
+        Event::MousePressed(button) => w.write_all(&[TAG_MOUSE_PRESSED, button as u8]),
+        Event::MouseReleased(button) => w.write_all(&[TAG_MOUSE_RELEASED, button as u8]),
+        Event::Scrolled { x, y } => {
+            w.write_all(&[TAG_SCROLLED])?;
+            write_zigzag(w, x)?;
+            write_zigzag(w, y)
+        }
+    }
+}
+
+fn read_event(r: &mut impl Read) -> io::Result<Event> {
+    match read_u8(r)? {
+        TAG_KEY_PRESSED => Ok(Event::KeyPressed(read_key(r)?)),
+        TAG_KEY_RELEASED => Ok(Event::KeyReleased(read_key(r)?)),
+        TAG_CHAR => {
+            let c = read_varint(r)?;
+            u32::try_from(c)
+                .ok()
+                .and_then(char::from_u32)
+                .map(Event::Char)
+                .ok_or_else(|| invalid(format!("bad character {c:#x}")))
+        }
+        TAG_ACTION_PRESSED => Ok(Event::ActionPressed(read_action(r)?)),
+        TAG_ACTION_RELEASED => Ok(Event::ActionReleased(read_action(r)?)),
+        TAG_MOUSE_MOVED => Ok(Event::MouseMoved {
+            x: read_zigzag(r)?,
+            y: read_zigzag(r)?,
+        }),
+        TAG_MOUSE_PRESSED => Ok(Event::MousePressed(read_button(r)?)),
+        TAG_MOUSE_RELEASED => Ok(Event::MouseReleased(read_button(r)?)),
+        TAG_SCROLLED => Ok(Event::Scrolled {
+            x: read_zigzag(r)?,
+            y: read_zigzag(r)?,
+        }),
+        tag => Err(invalid(format!("unknown event tag {tag}"))),
+    }
+}
+
+fn read_button(r: &mut impl Read) -> io::Result<MouseButton> {
+    let index = read_u8(r)?;
+    MouseButton::ALL
+        .get(index as usize)
+        .copied()
+        .ok_or_else(|| invalid(format!("unknown mouse button {index}")))
+}
+
+fn read_action(r: &mut impl Read) -> io::Result<u8> {
+    let index = read_u8(r)?;
+    match (index as usize) < MAX_ACTIONS {
+        true => Ok(index),
+        false => Err(invalid(format!("unknown action {index}"))),
+    }
+}
+
+fn read_key(r: &mut impl Read) -> io::Result<Key> {
+    let index = read_u8(r)?;
+    Key::ALL
+        .get(index as usize)
+        .copied()
+        .ok_or_else(|| invalid(format!("unknown key {index}")))
+}
+
+fn read_u8(r: &mut impl Read) -> io::Result<u8> {
+    let mut byte = [0];
+    r.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+/// LEB128
+fn write_varint(w: &mut impl Write, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return w.write_all(&[byte]);
+        }
+        w.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_varint(r: &mut impl Read) -> io::Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = read_u8(r)?;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid("varint too long"))
+}
+
+/// Small negative numbers stay small: 0, -1, 1, -2, 2... become 0, 1, 2, 3, 4...
+fn write_zigzag(w: &mut impl Write, value: i32) -> io::Result<()> {
+    write_varint(w, ((value << 1) ^ (value >> 31)) as u32 as u64)
+}
+
+fn read_zigzag(r: &mut impl Read) -> io::Result<i32> {
+    let value = u32::try_from(read_varint(r)?).map_err(|_| invalid("coordinate too big"))?;
+    Ok((value >> 1) as i32 ^ -((value & 1) as i32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Action;
+
+    #[test]
+    fn roundtrip() {
+        let recording = Recording {
+            init: 0xdead_beef_0123_4567,
+            ticks: vec![
+                vec![],
+                vec![Event::KeyPressed(Key::Up)],
+                vec![],
+                vec![],
+                vec![Event::KeyPressed(Key::Left), Event::KeyPressed(Key::Z)],
+                vec![Event::KeyReleased(Key::Z), Event::ActionPressed(3)],
+                vec![Event::Char('é'), Event::Char('🐍')],
+                vec![
+                    Event::MouseMoved { x: -5, y: 300 },
+                    Event::MousePressed(MouseButton::Right),
+                    Event::Scrolled { x: 0, y: -2 },
+                    Event::MouseReleased(MouseButton::Right),
+                ],
+            ],
+            actions: true,
+        };
+        let mut bytes = Vec::new();
+        recording.write(&mut bytes).unwrap();
+        assert_eq!(Recording::read(bytes.as_slice()).unwrap(), recording);
+    }
+
+    #[test]
+    fn quiet_ticks_are_cheap() {
+        let recording = Recording {
+            init: 0,
+            ticks: vec![vec![]; 100_000],
+            actions: true,
+        };
+        let mut bytes = Vec::new();
+        recording.write(&mut bytes).unwrap();
+        assert!(bytes.len() < 20, "{} bytes", bytes.len());
+        assert_eq!(Recording::read(bytes.as_slice()).unwrap(), recording);
+    }
+
+    #[test]
+    fn version_1() {
+        let mut bytes = b"BWRC\x01".to_vec();
+        bytes.extend(7u64.to_le_bytes());
+        bytes.extend([2, 1, TAG_KEY_PRESSED, Key::Up as u8, 0, 0]);
+        let recording = Recording::read(bytes.as_slice()).unwrap();
+        assert_eq!(recording.init, 7);
+        assert_eq!(
+            recording.ticks,
+            [vec![], vec![], vec![Event::KeyPressed(Key::Up)]]
+        );
+        assert!(!recording.actions);
+    }
+
+    #[test]
+    fn actions_from_bindings() {
+        let actions = &[Action {
+            name: "Jump",
+            keys: &[Key::Up, Key::W],
+        }];
+        let bindings = Bindings::new(actions);
+        let mut recording = Recording {
+            init: 0,
+            ticks: vec![
+                vec![Event::KeyPressed(Key::Up)],
+                vec![Event::KeyPressed(Key::W), Event::KeyReleased(Key::Up)],
+                vec![Event::KeyReleased(Key::W)],
+            ],
+            actions: false,
+        };
+        recording.add_actions(&bindings);
+        assert!(recording.actions);
+        assert_eq!(
+            recording.ticks,
+            [
+                vec![Event::KeyPressed(Key::Up), Event::ActionPressed(0)],
+                vec![
+                    Event::KeyPressed(Key::W),
+                    Event::ActionPressed(0),
+                    Event::KeyReleased(Key::Up)
+                ],
+                vec![Event::KeyReleased(Key::W), Event::ActionReleased(0)],
+            ]
+        );
+
+        // newer recordings have them already
+        let before = recording.clone();
+        recording.add_actions(&bindings);
+        assert_eq!(recording, before);
+    }
+
+    #[test]
+    fn unknown_actions() {
+        let mut recording = Recording {
+            init: 0,
+            ticks: vec![
+                vec![Event::ActionPressed(63)],
+                vec![Event::ActionReleased(2)],
+            ],
+            actions: true,
+        };
+        assert!(recording.check_actions(64).is_ok());
+        assert!(recording.check_actions(63).is_err());
+
+        // past what `Held` has bits for, whatever the game
+        recording.ticks[0] = vec![Event::ActionPressed(200)];
+        let mut bytes = Vec::new();
+        recording.write(&mut bytes).unwrap();
+        let err = Recording::read(bytes.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn varints() {
+        for value in [0, 1, 127, 128, 300, u32::MAX.into(), u64::MAX] {
+            let mut bytes = Vec::new();
+            write_varint(&mut bytes, value).unwrap();
+            assert_eq!(read_varint(&mut bytes.as_slice()).unwrap(), value);
+        }
+        for value in [0, -1, 1, -64, 64, i32::MIN, i32::MAX] {
+            let mut bytes = Vec::new();
+            write_zigzag(&mut bytes, value).unwrap();
+            assert_eq!(read_zigzag(&mut bytes.as_slice()).unwrap(), value);
+        }
+    }
+}
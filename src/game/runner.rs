@@ -0,0 +1,910 @@
+//! The loop behind `Game::run`.
+
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::BufWriter,
+    marker::PhantomData,
+    time::{Duration, Instant},
+};
+
+use super::{
+    Color, Event, Frame, Game, Held, Key, KeyRepeat, Output,
+    action::Bindings,
+    backend::Backend,
+    font,
+    held::MAX_ACTIONS,
+    history::{Entry, History},
+    options::Options,
+    recording::{Recorder, Recording},
+    saves::{self, Saves, Slot},
+};
+use crate::password;
+
+/// Speeds F4 and F5 step through, as fractions of `Game::TICK_RATE`.
+const SPEEDS: [(u32, u32); 6] = [(1, 4), (1, 2), (1, 1), (2, 1), (4, 1), (8, 1)];
+const NORMAL_SPEED: usize = 2;
+
+/// Every key `hotkey` takes. Releasing them doesn't reach the game either.
+const HOTKEYS: &[Key] = &[
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+];
+
+/// How far back F6 can go.
+const HISTORY_SECONDS: usize = 60;
+
+/// How many frames a notice stays up for.
+const NOTICE_FRAMES: u32 = 120;
+
+/// Runs a game, adding debugging controls no game has to know about.
+///
+/// # Hotkeys
+///
+/// Runs from the command line (`Game::run`) have hotkeys unless the game turns them off with
+/// `Game::HOTKEYS`. They take F1 to F12, which then never reach the game, or recordings. Other
+/// runs, like `Game::run_on` and `headless`, pass every key on.
+///
+/// - F1 shows the state, raw and split into the fields of `Game::debug_fields`, with the tick
+///   and frame rate.
+/// - F2 pauses and resumes. While paused the last frame stays up, along with the state's
+///   password (see `password`) for sharing it. Input during a pause isn't lost, but the game
+///   only sees what is held differently afterwards, not every press and release.
+/// - F3 runs exactly one tick while paused.
+/// - F4 and F5 slow down and speed up, from 0.25x to 8x.
+/// - F6 pauses and goes back one second, F7 goes forward one second again. Resuming or stepping
+///   plays on from there and forgets the old future. A scrubber shows where in the last minute
+///   we are.
+/// - F8 picks the next quicksave slot, F9 saves to it and F10 loads from it, which starts the
+///   history over. See `saves` for where they go.
+/// - F11 switches between fullscreen and a window.
+/// - F12 saves the current frame as `<name>-<tick>.png` in the working directory.
+pub struct Runner<G: Game> {
+    state: u64,
+    tick: u64,
+    /// As the player has it now. Going back in time doesn't change what their hands are doing.
+    held: Held,
+    /// Only for games with `KEY_REPEAT`.
+    repeater: Option<Repeater>,
+    bindings: Bindings,
+    /// Whether F1 to F12 are ours.
+    hotkeys: bool,
+    paused: bool,
+    /// Ticks requested with F3 that haven't run yet.
+    steps: u32,
+    /// Index into `SPEEDS`.
+    speed: usize,
+    history: History,
+    /// F11 was pressed, the backend hasn't been told yet.
+    toggle_fullscreen: bool,
+    /// Whether F1 has turned on the debug overlay.
+    overlay: bool,
+    fps: Fps,
+    /// Quicksave slot F9 and F10 use, counting from 0.
+    slot: usize,
+    /// Opened the first time they're needed.
+    saves: Option<Saves>,
+    /// Something to tell the player, and for how many more frames.
+    notice: Option<(String, u32)>,
+    /// For the backend's `message`, it hasn't been told yet.
+    messages: Vec<String>,
+    /// Ticks still to be played back. Once empty the player takes over.
+    replay: VecDeque<Vec<Event>>,
+    recorder: Option<Recorder<BufWriter<File>>>,
+    phantom: PhantomData<G>,
+}
+
+impl<G: Game> Runner<G> {
+    pub fn new(state: u64) -> Self {
+        const { assert!(G::TICK_RATE > 0, "Game::TICK_RATE must not be zero") };
+        const {
+            assert!(
+                G::ACTIONS.len() <= MAX_ACTIONS,
+                "Game::ACTIONS can have at most 64 actions"
+            )
+        };
+        Self {
+            state,
+            tick: 0,
+            held: Held::default(),
+            repeater: G::KEY_REPEAT.map(Repeater::new),
+            bindings: Bindings::new(G::ACTIONS),
+            hotkeys: false,
+            paused: false,
+            steps: 0,
+            speed: NORMAL_SPEED,
+            history: History::new(G::TICK_RATE as usize * HISTORY_SECONDS),
+            toggle_fullscreen: false,
+            overlay: false,
+            fps: Fps::new(),
+            slot: 0,
+            saves: None,
+            notice: None,
+            messages: Vec::new(),
+            replay: VecDeque::new(),
+            recorder: None,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Set up a runner the way the command line asks.
+    pub fn from_options(options: &Options) -> Result<Self, String> {
+        let mut runner = Self::new(options.state.unwrap_or_else(G::init));
+        runner.hotkeys = G::HOTKEYS;
+
+        let default_bindings = saves::dir().join(format!("{}.bindings", G::NAME.to_lowercase()));
+        let bindings = match &options.bindings {
+            Some(path) => Some(path),
+            None => Some(&default_bindings).filter(|path| path.exists()),
+        };
+        if let Some(path) = bindings {
+            runner.bindings = Bindings::load(path, G::ACTIONS)
+                .map_err(|err| format!("failed to load {}: {err}", path.display()))?;
+        }
+
+        if let Some(path) = &options.replay {
+            let mut recording = Recording::load(path)
+                .map_err(|err| format!("failed to load {}: {err}", path.display()))?;
+            recording.add_actions(&runner.bindings);
+            recording
+                .check_actions(G::ACTIONS.len())
+                .map_err(|err| format!("can't replay {}: {err}", path.display()))?;
+            runner.state = recording.init;
+            runner.replay = recording.ticks.into();
+        }
+
+        if let Some(path) = &options.record {
+            let recorder = Recorder::create(path, runner.state)
+                .map_err(|err| format!("failed to create {}: {err}", path.display()))?;
+            runner.recorder = Some(recorder);
+        }
+
+        Ok(runner)
+    }
+
+    /// Tick until the backend closes, returns the final state.
+    ///
+    /// Realtime backends get `G::TICK_RATE` ticks per second however often they present, so
+    /// a frame may show the same tick twice or skip some. Other backends get one tick per frame.
+    pub fn run(mut self, backend: &mut impl Backend) -> u64 {
+        self.run_until_closed(backend);
+
+        if let Some(recorder) = self.recorder.take()
+            && let Err(err) = recorder.finish()
+        {
+            self.messages
+                .push(format!("failed to finish recording: {err}"));
+        }
+        for text in self.messages.drain(..) {
+            backend.message(&text);
+        }
+
+        self.state
+    }
+
+    fn run_until_closed(&mut self, backend: &mut impl Backend) {
+        let mut clock = Clock::new(G::TICK_RATE);
+        // events wait here until there is a tick to deliver them to
+        let mut pending = Vec::new();
+        let mut frame = Frame::new(G::WIDTH, G::HEIGHT);
+
+        while backend.is_open() {
+            let mut live = backend.poll();
+            if self.hotkeys {
+                live.retain(|event| !self.hotkey(event, &mut frame));
+            }
+            pending.extend(live);
+            if self.paused {
+                // a tap while paused would otherwise be a press and release in the same tick
+                let mut after = self.held;
+                after.apply(&pending);
+                pending = self.held.changes(&after);
+            }
+            if std::mem::take(&mut self.toggle_fullscreen) {
+                backend.toggle_fullscreen();
+            }
+            for text in self.messages.drain(..) {
+                backend.message(&text);
+            }
+
+            let (num, den) = SPEEDS[self.speed];
+            clock.set_speed(num, den);
+            let due = if backend.is_realtime() {
+                clock.due(Instant::now())
+            } else {
+                1
+            };
+            let due = if self.paused {
+                std::mem::take(&mut self.steps)
+            } else {
+                due
+            };
+            for _ in 0..due {
+                frame = self.tick(std::mem::take(&mut pending));
+            }
+
+            self.fps.frame(Instant::now());
+            backend.present(&self.hud(&frame));
+            if let Some((_, frames)) = &mut self.notice {
+                *frames -= 1;
+                if *frames == 0 {
+                    self.notice = None;
+                }
+            }
+        }
+    }
+
+    fn tick(&mut self, live: Vec<Event>) -> Frame {
+        if self.history.is_rewound() {
+            self.leave_timeline("went back in time");
+        }
+
+        let events = match self.replay.pop_front() {
+            Some(events) => events,
+            None => {
+                // repeats go first, so a key released this tick ends up released
+                let live = match &self.repeater {
+                    Some(repeater) => [repeater.due(self.tick), live].concat(),
+                    None => live,
+                };
+                self.bindings.translate(&self.held, &live)
+            }
+        };
+        if let Some(repeater) = &mut self.repeater {
+            repeater.track(self.tick, &events);
+        }
+
+        self.record(&events);
+        self.history.push(Entry {
+            tick: self.tick,
+            state: self.state,
+            held: self.held,
+            events: events.clone(),
+        });
+
+        let (next, frame) = G::step(self.state, self.tick, &self.held, &events);
+        self.held.apply(&events);
+        self.state = next;
+
+        self.tick += 1;
+        frame
+    }
+
+    /// Returns whether `event` was one of ours.
+    fn hotkey(&mut self, event: &Event, frame: &mut Frame) -> bool {
+        let key = match event {
+            Event::KeyPressed(key) => key,
+            Event::KeyReleased(key) => return HOTKEYS.contains(key),
+            _ => return false,
+        };
+        let second = G::TICK_RATE as isize;
+        match key {
+            Key::F1 => self.overlay = !self.overlay,
+            Key::F2 => self.paused = !self.paused,
+            Key::F3 if self.paused => self.steps += 1,
+            Key::F3 => {}
+            Key::F4 => self.speed = self.speed.saturating_sub(1),
+            Key::F5 => self.speed = (self.speed + 1).min(SPEEDS.len() - 1),
+            Key::F6 => self.seek(-second, frame),
+            Key::F7 => self.seek(second, frame),
+            Key::F8 => {
+                self.slot = (self.slot + 1) % saves::SLOTS;
+                self.notify(format!("SLOT {}", self.slot + 1));
+            }
+            Key::F9 => self.quicksave(),
+            Key::F10 => self.quickload(frame),
+            Key::F11 => self.toggle_fullscreen = true,
+            Key::F12 => self.snapshot(frame),
+            _ => return false,
+        }
+        true
+    }
+
+    fn quicksave(&mut self) {
+        let slot = Slot {
+            tick: self.tick,
+            state: self.state,
+        };
+        let number = self.slot + 1;
+        let saved = self.saves().and_then(|saves| {
+            saves
+                .set(number - 1, slot)
+                .map_err(|err| format!("failed to save {}: {err}", saves.path().display()))
+        });
+        match saved {
+            Ok(()) => self.notify(format!("SAVED TO SLOT {number}")),
+            Err(err) => self.notify(err),
+        }
+    }
+
+    /// Jump to the state in the current slot. Paused, the frame shows the tick after it, as
+    /// that is the soonest the game draws it.
+    fn quickload(&mut self, frame: &mut Frame) {
+        let number = self.slot + 1;
+        let slot = match self.saves().map(|saves| saves.get(number - 1)) {
+            Ok(Some(slot)) => slot,
+            Ok(None) => return self.notify(format!("SLOT {number} IS EMPTY")),
+            Err(err) => return self.notify(err),
+        };
+        self.leave_timeline("loaded a quicksave");
+        // the ticks before it didn't lead here, so going back into them would be nonsense
+        self.history.clear();
+        self.state = slot.state;
+        self.jump_to(slot.tick);
+        (_, *frame) = G::step(slot.state, slot.tick, &self.held, &[]);
+        self.notify(format!("LOADED SLOT {number}"));
+    }
+
+    fn saves(&mut self) -> Result<&mut Saves, String> {
+        if self.saves.is_none() {
+            let saves =
+                Saves::open(G::NAME).map_err(|err| format!("failed to load saves: {err}"))?;
+            self.saves = Some(saves);
+        }
+        Ok(self.saves.as_mut().unwrap())
+    }
+
+    /// The replay and recording follow one unbroken run of ticks. Once that is broken neither is
+    /// any use.
+    fn leave_timeline(&mut self, why: &str) {
+        self.replay.clear();
+        if self.recorder.take().is_some() {
+            self.messages.push(format!("recording stopped: {why}"));
+        }
+    }
+
+    /// Show `text` for a little while, and pass it on to the backend's `message` too.
+    fn notify(&mut self, text: String) {
+        self.messages.push(text.clone());
+        self.notice = Some((text, NOTICE_FRAMES));
+    }
+
+    /// Travel `delta` ticks through history, pausing so there is time to look around.
+    fn seek(&mut self, delta: isize, frame: &mut Frame) {
+        self.paused = true;
+        self.steps = 0;
+        let Some(entry) = self.history.seek(delta) else {
+            return;
+        };
+        // running the tick again is cheaper than keeping every frame around
+        let (state, replayed) = G::step(entry.state, entry.tick, &entry.held, &entry.events);
+        let tick = entry.tick + 1;
+        self.state = state;
+        self.jump_to(tick);
+        *frame = replayed;
+    }
+
+    /// Carry on from `tick`, elsewhere in time.
+    fn jump_to(&mut self, tick: u64) {
+        self.tick = tick;
+        if let Some(repeater) = &mut self.repeater {
+            repeater.restart(tick);
+        }
+    }
+
+    /// The game's frame, plus whatever the runner has to say on top of it.
+    fn hud(&self, frame: &Frame) -> Frame {
+        let mut status = Vec::new();
+        if let Some((notice, _)) = &self.notice {
+            status.push(notice.clone());
+        }
+        if self.paused {
+            status.push("PAUSED".to_owned());
+            status.push(password::encode(self.state));
+        }
+        if self.speed != NORMAL_SPEED {
+            let (num, den) = SPEEDS[self.speed];
+            status.push(match den {
+                1 => format!("{num}x"),
+                _ => format!("{num}/{den}x"),
+            });
+        }
+        let behind = self.history.len() - self.history.cursor();
+        if behind > 0 {
+            let seconds = behind as f32 / G::TICK_RATE as f32;
+            status.push(format!("-{seconds:.1}s"));
+        }
+        let overlay = self.overlay.then(|| self.overlay_text());
+        let scrubber = self.paused && !self.history.is_empty();
+        if status.is_empty() && overlay.is_none() && !scrubber {
+            return frame.clone();
+        }
+
+        let mut output = Output::<G>::new();
+        let mut y = 0;
+        if !status.is_empty() {
+            y = panel(&mut output, y, &status.join(" "));
+        }
+        if let Some(text) = &overlay {
+            panel(&mut output, y, text);
+        }
+        if scrubber {
+            self.scrubber(&mut output);
+        }
+
+        let mut frame = frame.clone();
+        output.write_to(&mut frame.pixels);
+        frame
+    }
+
+    /// The state, raw and as the game's own fields if it names them.
+    fn overlay_text(&self) -> String {
+        let mut text = format!(
+            "TICK {} FPS {}\n{:#018x}\n",
+            self.tick,
+            self.fps.value(),
+            self.state
+        );
+        for half in [self.state >> 32, self.state & 0xffff_ffff] {
+            let bytes = (0..4)
+                .rev()
+                .map(|i| format!("{:08b}", (half >> (i * 8)) & 0xff));
+            text += &bytes.collect::<Vec<_>>().join(" ");
+            text.push('\n');
+        }
+
+        let fields: Vec<_> = G::debug_fields(self.state)
+            .into_iter()
+            .map(|field| format!("{} {}/{}", field.name, field.value, field.cardinality))
+            .collect();
+        let column = fields
+            .iter()
+            .map(|field| field.len() + 1)
+            .max()
+            .unwrap_or(1);
+        let columns = (G::WIDTH.saturating_sub(4) / (column * font::ADVANCE as usize)).max(1);
+        for row in fields.chunks(columns) {
+            for field in row {
+                text += &format!("{field:column$}");
+            }
+            text.push('\n');
+        }
+        text.trim_end().to_owned()
+    }
+
+    /// A bar along the bottom: the whole of it is the history we keep, the filled part is what
+    /// we have, and the marker is where we are in it.
+    fn scrubber(&self, output: &mut Output<'_, G>) {
+        const HEIGHT: u32 = 6;
+        let width = (G::WIDTH as u32).saturating_sub(4);
+        let y = G::HEIGHT as i32 - HEIGHT as i32 - 2;
+        let capacity = G::TICK_RATE as usize * HISTORY_SECONDS;
+        let at = |ticks: usize| 2 + (ticks as u64 * width as u64 / capacity as u64) as i32;
+        let kept = at(self.history.len());
+        let now = at(self.history.cursor());
+
+        output.rect(2, y, width, HEIGHT, Color::BLACK.with_alpha(160));
+        output.rect(2, y, (kept - 2) as u32, HEIGHT, Color::WHITE.with_alpha(64));
+        output.rect(now - 1, y - 1, 2, HEIGHT + 2, Color::WHITE);
+    }
+
+    fn snapshot(&mut self, frame: &Frame) {
+        let path = format!("{}-{}.png", G::NAME.to_lowercase(), self.tick);
+        match frame.save(&path) {
+            Ok(()) => self.notify(format!("saved {path}")),
+            Err(err) => self.notify(format!("failed to save {path}: {err}")),
+        }
+    }
+
+    fn record(&mut self, events: &[Event]) {
+        let Some(recorder) = &mut self.recorder else {
+            return;
+        };
+        if let Err(err) = recorder.record(events) {
+            self.messages.push(format!("recording stopped: {err}"));
+            self.recorder = None;
+        }
+    }
+}
+
+/// Presses held keys again for games with `Game::KEY_REPEAT`.
+///
+/// The repeats are decided here rather than by the backend so that every backend repeats the
+/// same way, and they are recorded like any other event.
+struct Repeater {
+    repeat: KeyRepeat,
+    /// Keys that are down, and the tick they went down on.
+    down: Vec<(Key, u64)>,
+}
+
+impl Repeater {
+    fn new(repeat: KeyRepeat) -> Self {
+        Self {
+            repeat: KeyRepeat {
+                delay: repeat.delay.max(1),
+                interval: repeat.interval.max(1),
+            },
+            down: Vec::new(),
+        }
+    }
+
+    /// Repeats of keys that were already down when `tick` started.
+    fn due(&self, tick: u64) -> Vec<Event> {
+        let KeyRepeat { delay, interval } = self.repeat;
+        self.down
+            .iter()
+            .filter(|&&(_, since)| {
+                let held = tick - since;
+                held >= delay.into() && (held - u64::from(delay)).is_multiple_of(interval.into())
+            })
+            .map(|&(key, _)| Event::KeyPressed(key))
+            .collect()
+    }
+
+    /// Keys that are still down count as pressed on `tick`, the ticks they went down on may not
+    /// have happened any more.
+    fn restart(&mut self, tick: u64) {
+        for (_, since) in &mut self.down {
+            *since = tick;
+        }
+    }
+
+    fn track(&mut self, tick: u64, events: &[Event]) {
+        for event in events {
+            match *event {
+                // a repeat, or a press we somehow missed the release of, doesn't restart the delay
+                Event::KeyPressed(key) if !self.down.iter().any(|&(down, _)| down == key) => {
+                    self.down.push((key, tick));
+                }
+                Event::KeyReleased(key) => self.down.retain(|&(down, _)| down != key),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Some text on a dark background across the top of the screen at `y`. Returns where the next
+/// panel would go.
+fn panel<G: Game>(output: &mut Output<'_, G>, y: i32, text: &str) -> i32 {
+    let [w, h] = font::text_size(text, 1);
+    output.rect(0, y, w + 4, h + 4, Color::BLACK.with_alpha(160));
+    output.text(2, y + 2, text, 1, Color::WHITE);
+    y + h as i32 + 4
+}
+
+/// Counts presented frames over the last second.
+struct Fps {
+    since: Option<Instant>,
+    frames: u32,
+    value: u32,
+}
+
+impl Fps {
+    fn new() -> Self {
+        Self {
+            since: None,
+            frames: 0,
+            value: 0,
+        }
+    }
+
+    fn frame(&mut self, now: Instant) {
+        let since = *self.since.get_or_insert(now);
+        self.frames += 1;
+        if now.saturating_duration_since(since) >= Duration::from_secs(1) {
+            self.value = self.frames;
+            self.frames = 0;
+            self.since = Some(now);
+        }
+    }
+
+    fn value(&self) -> u32 {
+        self.value
+    }
+}
+
+/// Turns wall clock time into a number of ticks to simulate.
+struct Clock {
+    tick_len: Duration,
+    /// Game time passes `speed.0 / speed.1` times as fast as wall clock time.
+    speed: (u32, u32),
+    last: Option<Instant>,
+    /// Game time that has passed but hasn't been simulated yet.
+    lag: Duration,
+}
+
+impl Clock {
+    /// After a stall it's better to slow down than to lurch forward, so no more than this many
+    /// ticks worth of wall clock time is made up for at once.
+    const MAX_CATCH_UP: u32 = 8;
+
+    fn new(rate: u32) -> Self {
+        Self {
+            tick_len: Duration::from_secs(1) / rate,
+            speed: (1, 1),
+            last: None,
+            lag: Duration::ZERO,
+        }
+    }
+
+    fn set_speed(&mut self, num: u32, den: u32) {
+        self.speed = (num, den);
+    }
+
+    /// How many ticks to run now. The very first call always gets one, so there is something
+    /// to show straight away.
+    fn due(&mut self, now: Instant) -> u32 {
+        let Some(last) = self.last.replace(now) else {
+            return 1;
+        };
+        let elapsed = now
+            .saturating_duration_since(last)
+            .min(self.tick_len * Self::MAX_CATCH_UP);
+        let (num, den) = self.speed;
+        self.lag += elapsed * num / den;
+
+        let due = (self.lag.as_nanos() / self.tick_len.as_nanos()) as u32;
+        self.lag -= self.tick_len * due;
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{
+        MouseButton,
+        headless::Headless,
+        test_games::{Counter, Namer, Pointer, Typist},
+    };
+
+    #[test]
+    fn clock() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        let mut clock = Clock::new(60);
+        assert_eq!(clock.due(at(0)), 1);
+        assert_eq!(clock.due(at(10)), 0);
+        assert_eq!(clock.due(at(20)), 1);
+        // 3.3ms left over from before
+        assert_eq!(clock.due(at(50)), 2);
+        assert_eq!(clock.due(at(10_000)), Clock::MAX_CATCH_UP);
+        assert_eq!(clock.due(at(10_010)), 0);
+
+        clock.set_speed(2, 1);
+        assert_eq!(clock.due(at(10_030)), 3);
+        clock.set_speed(1, 4);
+        assert_eq!(clock.due(at(10_100)), 1);
+    }
+
+    /// A runner with hotkeys, as `Game::run` makes them.
+    fn interactive<G: Game>(state: u64) -> Runner<G> {
+        Runner {
+            hotkeys: true,
+            ..Runner::new(state)
+        }
+    }
+
+    #[test]
+    fn pause_and_step() {
+        let press = |key| vec![Event::KeyPressed(key)];
+        let mut backend = Headless::new([
+            press(Key::F2),
+            press(Key::A),
+            vec![Event::KeyReleased(Key::A), Event::KeyPressed(Key::B)],
+            press(Key::F3),
+            vec![],
+            press(Key::F2),
+            press(Key::C),
+        ]);
+        let mut runner = interactive::<Counter>(0);
+        runner.run_until_closed(&mut backend);
+        // the step only delivers B, A came and went while paused, then resuming ticks once
+        // per poll again
+        assert_eq!((runner.state, runner.tick), (2, 3));
+        assert!(runner.held.is_key_down(Key::B));
+        assert!(!runner.held.is_key_down(Key::A));
+
+        let frames = backend.into_frames();
+        let blank = Frame::new(Counter::WIDTH, Counter::HEIGHT);
+        assert_ne!(frames[0], blank, "the pause should show");
+        assert_eq!(frames[1], frames[2]);
+        // only the game's dot, the pause is gone
+        assert_eq!(frames[6].pixels.iter().filter(|&&p| p != 0).count(), 1);
+    }
+
+    #[test]
+    fn scripts_have_no_hotkeys() {
+        let press = |key| vec![Event::KeyPressed(key)];
+        let mut backend = Headless::new([press(Key::F2), press(Key::F12)]);
+        assert_eq!(Counter::run_on(&mut backend, 0), 2);
+    }
+
+    #[test]
+    fn rewind() {
+        let press = |key| vec![Event::KeyPressed(key)];
+        let mut script = vec![press(Key::A); 70];
+        script.extend([press(Key::F6), press(Key::F6), press(Key::F7)]);
+        let mut backend = Headless::new(script.clone());
+        let mut runner = interactive::<Counter>(0);
+        runner.run_until_closed(&mut backend);
+        assert!(runner.paused);
+        // back to 10, back as far as there is, then forward from 1
+        assert_eq!((runner.state, runner.tick), (61, 61));
+
+        // playing on from the past forgets the future
+        script.extend([press(Key::F6), press(Key::F2), press(Key::B)]);
+        let mut backend = Headless::new(script);
+        let mut runner = interactive::<Counter>(0);
+        runner.run_until_closed(&mut backend);
+        // F6 lands on tick 1, then F2 and B each get a tick
+        assert_eq!((runner.state, runner.tick), (2, 3));
+        assert_eq!(runner.history.len(), 3);
+        assert!(!runner.history.is_rewound());
+    }
+
+    #[test]
+    fn overlay() {
+        let mut runner = Runner::<Counter>::new(0x8000_0000_0000_00ff);
+        runner.tick = 7;
+        assert_eq!(
+            runner.overlay_text(),
+            "TICK 7 FPS 0\n\
+             0x80000000000000ff\n\
+             10000000 00000000 00000000 00000000\n\
+             00000000 00000000 00000000 11111111"
+        );
+    }
+
+    #[test]
+    fn hud_fits_tiny_games() {
+        let mut backend = Headless::new([vec![], vec![Event::KeyPressed(Key::F1)]]);
+        let mut runner = interactive::<Namer>(0);
+        runner.run_until_closed(&mut backend);
+        runner.paused = true;
+        let frame = runner.hud(&Frame::new(Namer::WIDTH, Namer::HEIGHT));
+        assert_eq!((frame.width, frame.height), (1, 1));
+    }
+
+    /// Headless, but keeps what it's told instead of printing it.
+    struct Listening {
+        inner: Headless,
+        messages: Vec<String>,
+    }
+
+    impl Backend for Listening {
+        fn is_open(&self) -> bool {
+            self.inner.is_open()
+        }
+
+        fn poll(&mut self) -> Vec<Event> {
+            self.inner.poll()
+        }
+
+        fn present(&mut self, frame: &Frame) {
+            self.inner.present(frame);
+        }
+
+        fn message(&mut self, text: &str) {
+            self.messages.push(text.to_owned());
+        }
+
+        fn is_realtime(&self) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn notices_go_to_the_backend() {
+        let mut backend = Listening {
+            inner: Headless::new([vec![Event::KeyPressed(Key::F10)], vec![]]),
+            messages: Vec::new(),
+        };
+        let mut runner = interactive::<Counter>(0);
+        let path = std::env::temp_dir().join(format!("bitwise-notices-{}", std::process::id()));
+        // never written, loading an empty slot only complains
+        runner.saves = Some(Saves::open_at(path).unwrap());
+        runner.run_until_closed(&mut backend);
+        assert_eq!(backend.messages, ["SLOT 1 IS EMPTY"]);
+        assert!(runner.messages.is_empty());
+    }
+
+    #[test]
+    fn quicksave() {
+        let path = std::env::temp_dir().join(format!("bitwise-runner-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let press = |key| vec![Event::KeyPressed(key)];
+        let mut backend = Headless::new([
+            press(Key::A),
+            press(Key::F8),
+            press(Key::F9),
+            press(Key::A),
+            press(Key::F10),
+            press(Key::F8),
+            press(Key::F8),
+            press(Key::F8),
+            press(Key::F10),
+        ]);
+        let mut runner = interactive::<Counter>(0);
+        runner.saves = Some(Saves::open_at(&path).unwrap());
+        runner.run_until_closed(&mut backend);
+        // saved in slot 2 with A counted, loaded after the second A, loading slot 1 did nothing
+        assert_eq!(Saves::open_at(&path).unwrap().get(1).unwrap().state, 1);
+        assert_eq!(runner.slot, 0);
+        assert_eq!(runner.notice.unwrap().0, "SLOT 1 IS EMPTY");
+        // the first load took the state back from 2 to 1
+        assert_eq!(runner.state, 1);
+        // and forgot the ticks before it, only the five since are left
+        assert_eq!(runner.history.len(), 5);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn mouse_is_held_across_ticks() {
+        let mut backend = Headless::new([
+            vec![Event::MouseMoved { x: 1, y: 2 }],
+            vec![Event::MousePressed(MouseButton::Left)],
+            vec![],
+            vec![Event::MouseMoved { x: 3, y: 4 }],
+            vec![
+                Event::MouseReleased(MouseButton::Left),
+                Event::MouseMoved { x: 5, y: 6 },
+            ],
+        ]);
+        let mut runner = Runner::<Pointer>::new(0);
+        runner.run_until_closed(&mut backend);
+        assert_eq!(runner.state, 304);
+        assert_eq!(runner.held.mouse, Some([5, 6]));
+
+        // going back replays the tick with what was held then
+        let mut frame = Frame::new(Pointer::WIDTH, Pointer::HEIGHT);
+        runner.seek(-2, &mut frame);
+        assert_eq!(runner.state, 102);
+        assert_eq!(runner.held.mouse, Some([5, 6]));
+    }
+
+    #[test]
+    fn key_repeat() {
+        let mut script = vec![vec![Event::KeyPressed(Key::A)]];
+        script.extend(vec![vec![]; 7]);
+        script.push(vec![Event::KeyReleased(Key::A)]);
+        script.extend(vec![vec![]; 5]);
+        let mut backend = Headless::new(script);
+        let mut runner = Runner::<Typist>::new(0);
+        runner.run_until_closed(&mut backend);
+        // pressed on tick 0, repeated on ticks 3, 5 and 7, released on tick 8
+        assert_eq!(runner.state, 4);
+        assert!(!runner.held.is_key_down(Key::A));
+    }
+
+    #[test]
+    fn key_repeat_after_rewinding() {
+        let mut script = vec![vec![]; 10];
+        script.push(vec![Event::KeyPressed(Key::A)]);
+        script.extend([
+            vec![Event::KeyPressed(Key::F6)],
+            vec![Event::KeyPressed(Key::F2)],
+        ]);
+        script.extend(vec![vec![]; 4]);
+        let mut backend = Headless::new(script);
+        let mut runner = interactive::<Typist>(0);
+        runner.run_until_closed(&mut backend);
+        // back to before A went down, A still held repeats on tick 4 counting from tick 1
+        assert_eq!((runner.state, runner.tick), (1, 6));
+    }
+
+    #[test]
+    fn hotkeys_are_not_held() {
+        let mut backend = Headless::new([
+            vec![Event::KeyPressed(Key::F3), Event::KeyPressed(Key::B)],
+            vec![Event::KeyReleased(Key::F3)],
+        ]);
+        let mut runner = interactive::<Typist>(0);
+        runner.run_until_closed(&mut backend);
+        assert_eq!(runner.state, 1);
+        assert_eq!(runner.held.keys, 1 << Key::B as u8);
+    }
+}
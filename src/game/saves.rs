@@ -0,0 +1,195 @@
+//! Quicksave slots, one small text file per game.
+//!
+//! # Format
+//!
+//! ```text
+//! # comments and blank lines are ignored
+//! <slot> <tick> <state>
+//! ```
+//!
+//! Slots count from 1, the state is written in hex but anything `parse_state` takes is fine.
+//!
+//! Files live in `$BITWISE_SAVE_DIR` if it is set, otherwise `$XDG_DATA_HOME/bitwise`,
+//! `$HOME/.local/share/bitwise` or, failing all of those, the working directory.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::invalid;
+
+pub const SLOTS: usize = 4;
+
+/// A saved point in a game.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Slot {
+    pub tick: u64,
+    pub state: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Saves {
+    path: PathBuf,
+    slots: [Option<Slot>; SLOTS],
+}
+
+impl Saves {
+    /// The saves of the game called `name`. A missing file just means nothing was saved yet.
+    pub fn open(name: &str) -> io::Result<Self> {
+        let path = dir().join(format!("{}.saves", name.to_lowercase()));
+        Self::open_at(path)
+    }
+
+    pub fn open_at(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let slots = match fs::read_to_string(&path) {
+            Ok(text) => parse(&text)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Default::default(),
+            Err(err) => return Err(err),
+        };
+        Ok(Self { path, slots })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// `index` counts from 0.
+    pub fn get(&self, index: usize) -> Option<Slot> {
+        self.slots[index]
+    }
+
+    /// Overwrite a slot and write the file straight away.
+    pub fn set(&mut self, index: usize, slot: Slot) -> io::Result<()> {
+        self.slots[index] = Some(slot);
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, self.to_text())
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = "# slot tick state\n".to_owned();
+        for (index, slot) in self.slots.iter().enumerate() {
+            if let Some(Slot { tick, state }) = slot {
+                text += &format!("{} {tick} {state:#018x}\n", index + 1);
+            }
+        }
+        text
+    }
+}
+
+/// Where saves, and other per player files, go.
+pub fn dir() -> PathBuf {
+    let var = |name| std::env::var_os(name).filter(|value| !value.is_empty());
+    if let Some(dir) = var("BITWISE_SAVE_DIR") {
+        return dir.into();
+    }
+    if let Some(data) = var("XDG_DATA_HOME") {
+        return Path::new(&data).join("bitwise");
+    }
+    if let Some(home) = var("HOME") {
+        return Path::new(&home).join(".local/share/bitwise");
+    }
+    PathBuf::from(".")
+}
+
+fn parse(text: &str) -> io::Result<[Option<Slot>; SLOTS]> {
+    let mut slots = [None; SLOTS];
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let bad = |what| invalid(format!("line {}: {what} in {line:?}", number + 1));
+
+        let mut words = line.split_whitespace();
+        let mut word = |what| words.next().ok_or_else(|| bad(format!("missing {what}")));
+        let slot = word("slot")?;
+        let tick = word("tick")?;
+        let state = word("state")?;
+        if words.next().is_some() {
+            return Err(bad("too many words".to_owned()));
+        }
+
+        let index = match slot.parse::<usize>() {
+            Ok(slot @ 1..=SLOTS) => slot - 1,
+            _ => return Err(bad(format!("no slot {slot:?}"))),
+        };
+        slots[index] = Some(Slot {
+            tick: tick
+                .parse()
+                .map_err(|_| bad(format!("bad tick {tick:?}")))?,
+            state: parse_state(state).map_err(bad)?,
+        });
+    }
+    Ok(slots)
+}
+
+/// A state in decimal, or hex or binary with a `0x` or `0b` prefix. Underscores are allowed.
+pub fn parse_state(text: &str) -> Result<u64, String> {
+    let digits = text.replace('_', "");
+    let parsed = if let Some(hex) = digits.strip_prefix("0x") {
+        u64::from_str_radix(hex, 16)
+    } else if let Some(bin) = digits.strip_prefix("0b") {
+        u64::from_str_radix(bin, 2)
+    } else {
+        digits.parse()
+    };
+    parsed.map_err(|err| format!("bad state {text:?}: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn states() {
+        assert_eq!(parse_state("1234"), Ok(1234));
+        assert_eq!(parse_state("0xdead_BEEF"), Ok(0xdead_beef));
+        assert_eq!(parse_state("0b101"), Ok(5));
+        assert_eq!(parse_state(&u64::MAX.to_string()), Ok(u64::MAX));
+        assert!(parse_state("0x1_0000_0000_0000_0000").is_err());
+        assert!(parse_state("-1").is_err());
+        assert!(parse_state("").is_err());
+    }
+
+    #[test]
+    fn text_roundtrip() {
+        let mut saves = Saves::open_at("unused").unwrap();
+        saves.slots[0] = Some(Slot {
+            tick: 12,
+            state: 34,
+        });
+        saves.slots[3] = Some(Slot {
+            tick: 0,
+            state: u64::MAX,
+        });
+        let text = saves.to_text();
+        assert_eq!(
+            text,
+            "# slot tick state\n1 12 0x0000000000000022\n4 0 0xffffffffffffffff\n"
+        );
+        assert_eq!(parse(&text).unwrap(), saves.slots);
+
+        assert!(parse("0 1 2").is_err());
+        assert!(parse("5 1 2").is_err());
+        assert!(parse("1 1").is_err());
+        assert!(parse("1 1 2 3").is_err());
+    }
+
+    #[test]
+    fn files() {
+        let path = std::env::temp_dir().join(format!("bitwise-saves-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut saves = Saves::open_at(&path).unwrap();
+        assert_eq!(saves.get(1), None);
+        let slot = Slot { tick: 5, state: 6 };
+        saves.set(1, slot).unwrap();
+        assert_eq!(Saves::open_at(&path).unwrap().get(1), Some(slot));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,102 @@
+//! Small games for the tests of the runner, backends and drawing code.
+
+use super::{Color, Game, Input, KeyRepeat, MouseButton, Output, Typed};
+
+/// Counts key presses and draws a dot that far along the second row.
+pub struct Counter;
+
+impl Game for Counter {
+    const NAME: &'static str = "Counter";
+    const WIDTH: usize = 64;
+    const HEIGHT: usize = 16;
+
+    fn init() -> u64 {
+        0
+    }
+
+    fn tick(prev: u64, input: &Input<'_, Self>, output: &mut Output<'_, Self>) -> u64 {
+        let next = prev + input.get_keys_pressed().count() as u64;
+        output.rect(next as i32, 1, 1, 1, Color::rgb(1, 2, 3));
+        next
+    }
+}
+
+/// Counts key presses, repeats included.
+pub struct Typist;
+
+impl Game for Typist {
+    const NAME: &'static str = "Typist";
+    const WIDTH: usize = 16;
+    const HEIGHT: usize = 16;
+    const KEY_REPEAT: Option<KeyRepeat> = Some(KeyRepeat {
+        delay: 3,
+        interval: 2,
+    });
+
+    fn init() -> u64 {
+        0
+    }
+
+    fn tick(prev: u64, input: &Input<'_, Self>, _: &mut Output<'_, Self>) -> u64 {
+        prev + input.get_keys_pressed().count() as u64
+    }
+}
+
+/// Remembers where the mouse is while the left button is down, as `x * 100 + y`.
+pub struct Pointer;
+
+impl Game for Pointer {
+    const NAME: &'static str = "Pointer";
+    const WIDTH: usize = 16;
+    const HEIGHT: usize = 16;
+
+    fn init() -> u64 {
+        0
+    }
+
+    fn tick(prev: u64, input: &Input<'_, Self>, _: &mut Output<'_, Self>) -> u64 {
+        match input.mouse_position() {
+            Some([x, y]) if input.is_mouse_down(MouseButton::Left) => (x * 100 + y) as u64,
+            _ => prev,
+        }
+    }
+}
+
+/// Keeps the last eight characters typed, one per byte.
+pub struct Namer;
+
+impl Game for Namer {
+    const NAME: &'static str = "Namer";
+    const WIDTH: usize = 1;
+    const HEIGHT: usize = 1;
+
+    fn init() -> u64 {
+        0
+    }
+
+    fn tick(prev: u64, input: &Input<'_, Self>, _: &mut Output<'_, Self>) -> u64 {
+        input.get_typed().fold(prev, |name, typed| match typed {
+            Typed::Char(c) if c.is_ascii() => name << 8 | c as u64,
+            Typed::Char(_) => name,
+            Typed::Backspace => name >> 8,
+            Typed::Enter => 0,
+        })
+    }
+}
+
+/// Does nothing, for drawing on an `Output` of its size directly.
+pub struct Tiny;
+
+impl Game for Tiny {
+    const NAME: &'static str = "Tiny";
+    const WIDTH: usize = 12;
+    const HEIGHT: usize = 8;
+
+    fn init() -> u64 {
+        0
+    }
+
+    fn tick(prev: u64, _: &Input<'_, Self>, _: &mut Output<'_, Self>) -> u64 {
+        prev
+    }
+}
@@ -1,2 +1,6 @@
+// lets `#[derive(Encodable)]` name this crate from inside it
+extern crate self as bitwise_challenge_bddap;
+
 pub mod cheeky_encoding;
 pub mod game;
+pub mod password;
@@ -0,0 +1,148 @@
+//! Retro style passwords for states.
+//!
+//! A `u64` is 13 symbols of Crockford's base32 plus his mod 37 check symbol, written in groups
+//! of four: `0x0123_4567_89ab_cdef` is `028T-5CY4-TQKF-FA`.
+//!
+//! Decoding forgives the usual typing slips: case, missing or extra dashes and spaces, `O` for
+//! `0` and `I` or `L` for `1`. The check symbol catches any single wrong symbol and most
+//! swapped pairs.
+
+use std::fmt;
+
+const SYMBOLS: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+/// Only used for the check symbol, which can take 37 values.
+const CHECK_SYMBOLS: &[u8; 37] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ*~$=U";
+
+/// 13 symbols of 5 bits hold 65 bits, so the first symbol only goes up to `F`.
+const LEN: usize = 13;
+const GROUP: usize = 4;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// Not `LEN` symbols plus a check symbol. Holds how many symbols there were.
+    Length(usize),
+    /// Not something a password is made of.
+    Symbol(char),
+    /// The password is more than 64 bits.
+    Overflow,
+    /// The check symbol doesn't match, so something was mistyped.
+    Check,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Length(len) => write!(f, "expected {} symbols, found {len}", LEN + 1),
+            Error::Symbol(c) => write!(f, "{c:?} can't be part of a password"),
+            Error::Overflow => write!(f, "too big to be a state"),
+            Error::Check => write!(f, "check symbol doesn't match, something is mistyped"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub fn encode(state: u64) -> String {
+    let mut symbols: Vec<u8> = (0..LEN)
+        .rev()
+        .map(|i| SYMBOLS[(state >> (i * 5)) as usize & 31])
+        .collect();
+    symbols.push(CHECK_SYMBOLS[(state % 37) as usize]);
+
+    let groups: Vec<_> = symbols
+        .chunks(GROUP)
+        .map(|group| std::str::from_utf8(group).unwrap())
+        .collect();
+    groups.join("-")
+}
+
+pub fn decode(password: &str) -> Result<u64, Error> {
+    let symbols: Vec<char> = password
+        .chars()
+        .filter(|c| *c != '-' && !c.is_whitespace())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    let [data @ .., check] = symbols.as_slice() else {
+        return Err(Error::Length(0));
+    };
+    if data.len() != LEN {
+        return Err(Error::Length(symbols.len()));
+    }
+
+    let mut state = 0u64;
+    for (i, &c) in data.iter().enumerate() {
+        let value = value(c).ok_or(Error::Symbol(c))?;
+        if i == 0 && value > 15 {
+            return Err(Error::Overflow);
+        }
+        state = state << 5 | value;
+    }
+
+    let check = CHECK_SYMBOLS
+        .iter()
+        .position(|&symbol| symbol as char == *check)
+        .or_else(|| value(*check).map(|value| value as usize))
+        .ok_or(Error::Symbol(*check))?;
+    if check as u64 != state % 37 {
+        return Err(Error::Check);
+    }
+    Ok(state)
+}
+
+/// What a (capitalized) data symbol is worth, counting the look-alikes.
+fn value(c: char) -> Option<u64> {
+    let c = match c {
+        'O' => '0',
+        'I' | 'L' => '1',
+        _ => c,
+    };
+    let position = SYMBOLS.iter().position(|&symbol| symbol as char == c)?;
+    Some(position as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        for state in [0, 1, 36, 37, 0xdead_beef, u64::MAX / 3, u64::MAX] {
+            let password = encode(state);
+            assert_eq!(password.len(), 14 + 3);
+            assert_eq!(decode(&password), Ok(state));
+        }
+        assert_eq!(encode(0), "0000-0000-0000-00");
+        assert_eq!(encode(u64::MAX), "FZZZ-ZZZZ-ZZZZ-ZB");
+    }
+
+    #[test]
+    fn forgiving() {
+        let password = encode(0x0123_4567_89ab_cdef);
+        assert_eq!(password, "028T-5CY4-TQKF-FA");
+        let sloppy = password.to_lowercase().replace('-', " ").replace('0', "o");
+        assert_eq!(decode(&sloppy), Ok(0x0123_4567_89ab_cdef));
+        assert_eq!(decode("000000000001 l~"), Ok(33));
+    }
+
+    #[test]
+    fn mistakes() {
+        assert_eq!(decode(""), Err(Error::Length(0)));
+        assert_eq!(decode("0000-0000-0000-0"), Err(Error::Length(13)));
+        assert_eq!(decode("0000-0000-0000-U0"), Err(Error::Symbol('U')));
+        assert_eq!(decode("G000-0000-0000-00"), Err(Error::Overflow));
+        assert_eq!(decode("0000-0000-0000-01"), Err(Error::Check));
+
+        // every single wrong symbol is caught
+        let password: Vec<char> = encode(0x0123_4567_89ab_cdef).chars().collect();
+        for i in (0..password.len()).filter(|&i| password[i] != '-') {
+            for &wrong in SYMBOLS {
+                let mut typo = password.clone();
+                typo[i] = wrong as char;
+                if typo != password {
+                    let typo: String = typo.into_iter().collect();
+                    assert!(decode(&typo).is_err(), "{typo} was accepted");
+                }
+            }
+        }
+    }
+}